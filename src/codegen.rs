@@ -0,0 +1,178 @@
+use std::collections::HashSet;
+
+use rusqlite::Connection;
+use serde_json::Value as JsonValue;
+
+use crate::commands::introspection::{list_columns, list_tables};
+use crate::types::Result;
+
+/// Generates a TypeScript interface for every table of the main database, typed the way the
+/// plugin converts values: numbers for integers and reals, strings for text, byte arrays for
+/// blobs and `null` for nullable columns. Tables whose names map to the same interface name get
+/// a numeric suffix. The output can be written to a `.d.ts` file from a build step or a
+/// development command.
+pub fn generate_typescript(connection: &Connection) -> Result<String> {
+    let tables = list_tables(connection, "main")?;
+
+    let mut output = String::new();
+    let mut names = HashSet::new();
+    for table in tables {
+        let columns = list_columns(connection, "main", &table.name)?;
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        let name = interface_name(&table.name);
+        let mut unique_name = name.clone();
+        let mut suffix = 1;
+        while !names.insert(unique_name.clone()) {
+            suffix += 1;
+            unique_name = format!("{}{}", name, suffix);
+        }
+        output.push_str(&format!("export interface {} {{\n", unique_name));
+
+        // Only an INTEGER PRIMARY KEY, the alias of the rowid, is never NULL without NOT NULL.
+        let primary_key_count = columns
+            .iter()
+            .filter(|column| column.primary_key > 0)
+            .count();
+        for column in columns {
+            let is_rowid = primary_key_count == 1
+                && column.primary_key > 0
+                && column
+                    .decl_type
+                    .as_deref()
+                    .map_or(false, |decl_type| decl_type.eq_ignore_ascii_case("INTEGER"));
+            let not_null = column.not_null || is_rowid;
+            let mut typescript_type =
+                typescript_type(column.decl_type.as_deref().unwrap_or_default()).to_string();
+            if !not_null && typescript_type != "any" {
+                typescript_type.push_str(" | null");
+            }
+            output.push_str(&format!(
+                "  {}: {};\n",
+                property_name(&column.name),
+                typescript_type
+            ));
+        }
+        output.push_str("}\n");
+    }
+
+    Ok(output)
+}
+
+/// Maps a declared column type to a TypeScript type following the SQLite type affinity rules.
+pub fn typescript_type(decl_type: &str) -> &'static str {
+    let decl_type = decl_type.to_uppercase();
+    if decl_type.contains("INT") {
+        "number"
+    } else if decl_type.contains("CHAR") || decl_type.contains("CLOB") || decl_type.contains("TEXT")
+    {
+        "string"
+    } else if decl_type.contains("BLOB") {
+        "number[]"
+    } else if decl_type.is_empty() {
+        "any"
+    } else {
+        "number"
+    }
+}
+
+fn interface_name(table: &str) -> String {
+    let name = table
+        .split(|character: char| !character.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut characters = part.chars();
+            match characters.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + characters.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect::<String>();
+
+    if name.starts_with(|character: char| character.is_ascii_digit()) || name.is_empty() {
+        format!("Table{}", name)
+    } else {
+        name
+    }
+}
+
+fn property_name(column: &str) -> String {
+    let is_identifier = column.chars().next().map_or(false, |first| {
+        first.is_ascii_alphabetic() || first == '_' || first == '$'
+    }) && column
+        .chars()
+        .all(|character| character.is_ascii_alphanumeric() || character == '_' || character == '$');
+
+    if is_identifier {
+        column.to_string()
+    } else {
+        JsonValue::String(column.to_string()).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_typescript_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE user_accounts (id INTEGER PRIMARY KEY, name VARCHAR(40) NOT NULL, score REAL, avatar BLOB, \"display name\" TEXT, extra);
+                CREATE TABLE tags (label TEXT NOT NULL);",
+            )
+            .unwrap();
+
+        let typescript = generate_typescript(&connection).unwrap();
+
+        assert_eq!(
+            typescript,
+            r#"export interface Tags {
+  label: string;
+}
+
+export interface UserAccounts {
+  id: number;
+  name: string;
+  score: number | null;
+  avatar: number[] | null;
+  "display name": string | null;
+  extra: any;
+}
+"#
+        );
+    }
+
+    #[test]
+    fn generate_typescript_keys_and_names_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE memberships (user_id INTEGER, group_id INTEGER, PRIMARY KEY (user_id, group_id));
+                CREATE TABLE user_accounts (code TEXT PRIMARY KEY);
+                CREATE TABLE UserAccounts (id integer PRIMARY KEY);",
+            )
+            .unwrap();
+
+        let typescript = generate_typescript(&connection).unwrap();
+
+        assert_eq!(
+            typescript,
+            r#"export interface UserAccounts {
+  id: number;
+}
+
+export interface Memberships {
+  user_id: number | null;
+  group_id: number | null;
+}
+
+export interface UserAccounts2 {
+  code: string | null;
+}
+"#
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use std::cmp::Ordering;
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+
+#[cfg(feature = "unicode_collations")]
+use icu_normalizer::DecomposingNormalizerBorrowed;
+#[cfg(feature = "unicode_collations")]
+use icu_properties::{props::GeneralCategory, CodePointMapData};
+use rusqlite::Connection;
+
+use crate::types::ConnectionInitializer;
+
+/// Returns an initializer creating the collation `name` on a connection.
+pub fn collation<C>(name: &str, compare: C) -> ConnectionInitializer
+where
+    C: Fn(&str, &str) -> Ordering + Send + Sync + RefUnwindSafe + 'static,
+{
+    let name = name.to_string();
+    let compare = Arc::new(compare);
+    Arc::new(move |connection: &Connection| {
+        let compare = compare.clone();
+        connection.create_collation(&name, move |left, right| compare(left, right))
+    })
+}
+
+/// Compares text ignoring case and accents, so that `émile` sorts between `Emil` and `Emma`.
+/// Texts that only differ in case or accents are equal, for `DISTINCT` and `UNIQUE` as well.
+#[cfg(feature = "unicode_collations")]
+pub fn unicode_ci(left: &str, right: &str) -> Ordering {
+    fold(left).cmp(&fold(right))
+}
+
+/// Compares text like [`unicode_ci`], but compares runs of ASCII digits by their numeric value,
+/// so that `file 9` sorts before `file 10`.
+#[cfg(feature = "unicode_collations")]
+pub fn natural(left: &str, right: &str) -> Ordering {
+    let mut left_chunks = chunks(left);
+    let mut right_chunks = chunks(right);
+
+    loop {
+        let ordering = match (left_chunks.next(), right_chunks.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(left_chunk), Some(right_chunk)) => {
+                match (is_number(left_chunk), is_number(right_chunk)) {
+                    (true, true) => compare_numbers(left_chunk, right_chunk),
+                    _ => fold(left_chunk).cmp(&fold(right_chunk)),
+                }
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Decomposes the text, drops the nonspacing marks, such as accents, left by the decomposition
+/// and lowercases it.
+#[cfg(feature = "unicode_collations")]
+fn fold(text: &str) -> String {
+    DecomposingNormalizerBorrowed::new_nfd()
+        .normalize(text)
+        .chars()
+        .filter(|character| {
+            CodePointMapData::<GeneralCategory>::new().get(*character)
+                != GeneralCategory::NonspacingMark
+        })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Splits the text into alternating runs of ASCII digits and other characters.
+#[cfg(feature = "unicode_collations")]
+fn chunks(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let first = rest.chars().next()?;
+        let length = rest
+            .find(|character: char| character.is_ascii_digit() != first.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(length);
+        rest = tail;
+        Some(chunk)
+    })
+}
+
+#[cfg(feature = "unicode_collations")]
+fn is_number(chunk: &str) -> bool {
+    chunk.starts_with(|character: char| character.is_ascii_digit())
+}
+
+#[cfg(feature = "unicode_collations")]
+fn compare_numbers(left: &str, right: &str) -> Ordering {
+    let left_digits = left.trim_start_matches('0');
+    let right_digits = right.trim_start_matches('0');
+    left_digits
+        .len()
+        .cmp(&right_digits.len())
+        .then_with(|| left_digits.cmp(right_digits))
+        .then_with(|| left.len().cmp(&right.len()))
+}
+
+#[cfg(all(test, feature = "unicode_collations"))]
+mod tests {
+    use super::*;
+
+    fn sorted(connection: &Connection, collation: &str) -> Vec<String> {
+        let mut statement = connection
+            .prepare(&format!(
+                "SELECT name FROM names ORDER BY name COLLATE {}",
+                collation
+            ))
+            .unwrap();
+        let names = statement
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<String>>>()
+            .unwrap();
+        names
+    }
+
+    #[test]
+    fn collation_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        collation("UNICODE_CI", unicode_ci)(&connection).unwrap();
+        collation("NATURAL_CI", natural)(&connection).unwrap();
+        collation("REVERSE", |left: &str, right: &str| right.cmp(left))(&connection).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE names (name TEXT);
+                INSERT INTO names VALUES ('Zoë'), ('émile'), ('Emma'), ('emil'), ('Élodie'),
+                    ('file 10'), ('file 9'), ('file 009b');",
+            )
+            .unwrap();
+
+        assert_eq!(
+            sorted(&connection, "UNICODE_CI"),
+            vec![
+                "Élodie",
+                "emil",
+                "émile",
+                "Emma",
+                "file 009b",
+                "file 10",
+                "file 9",
+                "Zoë"
+            ]
+        );
+        assert_eq!(
+            sorted(&connection, "NATURAL_CI"),
+            vec![
+                "Élodie",
+                "emil",
+                "émile",
+                "Emma",
+                "file 9",
+                "file 009b",
+                "file 10",
+                "Zoë"
+            ]
+        );
+        assert_eq!(sorted(&connection, "REVERSE")[0], "émile");
+
+        connection
+            .execute_batch("INSERT INTO names VALUES ('Emile'), ('EMMA');")
+            .unwrap();
+        let count: i64 = connection
+            .query_row(
+                "SELECT count(DISTINCT name COLLATE UNICODE_CI) FROM names",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 8);
+    }
+
+    #[test]
+    fn unicode_ci_test() {
+        assert_eq!(unicode_ci("Ärger", "arger"), Ordering::Equal);
+        assert_eq!(unicode_ci("שָׁלוֹם", "שלום"), Ordering::Equal);
+        assert_eq!(natural("File 9", "file 9"), Ordering::Equal);
+        assert_eq!(unicode_ci("Ärger", "Ärger"), Ordering::Equal);
+        assert_eq!(unicode_ci("Ärger", "Asche"), Ordering::Less);
+        assert_eq!(natural("v1.10", "v1.9"), Ordering::Greater);
+        assert_eq!(natural("a01", "a1"), Ordering::Greater);
+    }
+}
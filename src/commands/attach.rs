@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+
+use rusqlite::Connection;
+
+use crate::common::require_feature;
+use crate::error::Error;
+use crate::types::{AttachOptions, Result};
+
+/// A database attached to a connection, kept so that it is attached again when the connection
+/// is reopened.
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub path: String,
+    pub schema: String,
+    pub options: AttachOptions,
+}
+
+/// The databases attached to each connection, by connection name. They are kept when the
+/// connection is closed, to be attached again when it is reopened, until they are detached or
+/// forgotten.
+#[derive(Default)]
+pub struct Attachments(HashMap<String, Vec<Attachment>>);
+
+impl Attachments {
+    /// Attaches `attachment` to the connection `name` and keeps it, replacing the database kept
+    /// under the same schema name, which raw SQL may have detached. Schema names compare like
+    /// SQLite does, ignoring ASCII case.
+    pub fn attach(
+        &mut self,
+        connection: &Connection,
+        name: &str,
+        attachment: Attachment,
+    ) -> Result<()> {
+        execute_attach(connection, &attachment)?;
+        let attachments = self.0.entry(name.to_string()).or_default();
+        attachments.retain(|existing| !existing.schema.eq_ignore_ascii_case(&attachment.schema));
+        attachments.push(attachment);
+        Ok(())
+    }
+
+    pub fn detach(&mut self, connection: &Connection, name: &str, schema: &str) -> Result<()> {
+        execute_detach(connection, schema)?;
+        if let Some(attachments) = self.0.get_mut(name) {
+            attachments.retain(|attachment| !attachment.schema.eq_ignore_ascii_case(schema));
+        }
+        Ok(())
+    }
+
+    /// Attaches the databases kept for the connection `name` to the connection reopened under
+    /// that name, forgetting those that fail, such as removed files, which are not created again.
+    pub fn reattach(&mut self, connection: &Connection, name: &str) {
+        if let Some(attachments) = self.0.get_mut(name) {
+            attachments.retain(|attachment| {
+                let mode = if attachment.options.read_only.unwrap_or(false) {
+                    "ro"
+                } else {
+                    "rw"
+                };
+                let path = format!("file:{}?mode={}", escape_uri_path(&attachment.path), mode);
+                attach_database(connection, &path, attachment).is_ok()
+            });
+        }
+    }
+
+    pub fn forget(&mut self, name: &str) {
+        self.0.remove(name);
+    }
+}
+
+/// Attaches the database file of `attachment` under its schema name, so that its tables can be
+/// queried as `schema.table`. Read-only databases are opened through a URI filename.
+fn execute_attach(connection: &Connection, attachment: &Attachment) -> Result<()> {
+    let path = if attachment.options.read_only.unwrap_or(false) {
+        format!("file:{}?mode=ro", escape_uri_path(&attachment.path))
+    } else {
+        attachment.path.clone()
+    };
+    attach_database(connection, &path, attachment)
+}
+
+fn attach_database(connection: &Connection, path: &str, attachment: &Attachment) -> Result<()> {
+    let result = match &attachment.options.key {
+        Some(key) => {
+            require_feature(cfg!(feature = "sqlcipher"), "sqlcipher")?;
+            connection.execute(
+                "ATTACH DATABASE ?1 AS ?2 KEY ?3",
+                [path, &attachment.schema, key],
+            )
+        }
+        None => connection.execute("ATTACH DATABASE ?1 AS ?2", [path, &attachment.schema]),
+    };
+    result.map_err(|error| Error::Database(error.to_string()))?;
+    Ok(())
+}
+
+fn execute_detach(connection: &Connection, schema: &str) -> Result<()> {
+    connection
+        .execute("DETACH DATABASE ?1", [schema])
+        .map_err(|error| Error::Database(error.to_string()))?;
+    Ok(())
+}
+
+/// Escapes the characters that end the path or start an escape sequence in a URI filename.
+fn escape_uri_path(path: &str) -> String {
+    path.replace('%', "%25")
+        .replace('?', "%3f")
+        .replace('#', "%23")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn execute_attach_test() {
+        let directory = std::env::temp_dir().join(format!("attach_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("catalog #1?.db");
+        let path = path.to_str().unwrap().to_string();
+
+        let catalog = Connection::open(&path).unwrap();
+        catalog
+            .execute_batch(
+                "CREATE TABLE products (id INTEGER PRIMARY KEY, label TEXT);
+                INSERT INTO products (label) VALUES ('book');",
+            )
+            .unwrap();
+        drop(catalog);
+
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE orders (id INTEGER PRIMARY KEY, product_id INTEGER);
+                INSERT INTO orders (product_id) VALUES (1);",
+            )
+            .unwrap();
+
+        let attachment = Attachment {
+            path: path.clone(),
+            schema: "catalog".to_string(),
+            options: AttachOptions {
+                read_only: Some(true),
+                key: None,
+            },
+        };
+        execute_attach(&connection, &attachment).unwrap();
+
+        let label: String = connection
+            .query_row(
+                "SELECT label FROM orders JOIN catalog.products ON products.id = orders.product_id",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(label, "book");
+        assert!(connection
+            .execute("INSERT INTO catalog.products (label) VALUES ('pen')", [])
+            .is_err());
+
+        execute_detach(&connection, "catalog").unwrap();
+        assert!(connection
+            .prepare("SELECT * FROM catalog.products")
+            .is_err());
+        assert!(execute_detach(&connection, "catalog").is_err());
+
+        let attachment = Attachment {
+            options: AttachOptions::default(),
+            ..attachment
+        };
+        execute_attach(&connection, &attachment).unwrap();
+        connection
+            .execute("INSERT INTO catalog.products (label) VALUES ('pen')", [])
+            .unwrap();
+        execute_detach(&connection, "catalog").unwrap();
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn attachments_reopen_test() {
+        let directory = std::env::temp_dir().join(format!("reattach_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let database = directory.join("main.db");
+        let attachment = |file: &str, schema: &str| Attachment {
+            path: directory.join(file).to_str().unwrap().to_string(),
+            schema: schema.to_string(),
+            options: AttachOptions::default(),
+        };
+
+        let mut attachments = Attachments::default();
+        let connection = Connection::open(&database).unwrap();
+        attachments
+            .attach(&connection, "main.db", attachment("catalog.db", "catalog"))
+            .unwrap();
+        attachments
+            .attach(&connection, "main.db", attachment("archive.db", "archive"))
+            .unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE catalog.products (id INTEGER PRIMARY KEY, label TEXT);
+                INSERT INTO catalog.products (label) VALUES ('book');",
+            )
+            .unwrap();
+        connection.close().unwrap();
+
+        let connection = Connection::open(&database).unwrap();
+        attachments.reattach(&connection, "main.db");
+        let label: String = connection
+            .query_row("SELECT label FROM catalog.products", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(label, "book");
+        connection.close().unwrap();
+
+        std::fs::remove_file(directory.join("archive.db")).unwrap();
+        let connection = Connection::open(&database).unwrap();
+        attachments.reattach(&connection, "main.db");
+        assert_eq!(attachments.0["main.db"].len(), 1);
+        assert!(connection
+            .prepare("SELECT * FROM archive.sqlite_schema")
+            .is_err());
+        assert!(!directory.join("archive.db").exists());
+        connection
+            .execute("INSERT INTO catalog.products (label) VALUES ('pen')", [])
+            .unwrap();
+
+        attachments
+            .detach(&connection, "main.db", "CATALOG")
+            .unwrap();
+        assert!(attachments.0["main.db"].is_empty());
+        connection.close().unwrap();
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
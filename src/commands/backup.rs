@@ -0,0 +1,250 @@
+#[cfg(feature = "backup")]
+use std::{thread, time::Duration};
+
+#[cfg(feature = "backup")]
+use rusqlite::backup::{Backup, StepResult};
+use rusqlite::Connection;
+
+#[cfg(not(feature = "backup"))]
+use crate::common::require_feature;
+#[cfg(feature = "backup")]
+use crate::error::Error;
+use crate::types::Result;
+
+/// Copies the main database of `connection` into `destination`, `pages_per_step` pages at a time
+/// or all at once if it is zero or negative, and hands the number of remaining pages and the
+/// total number of pages to `emit` after each step. Steps that find the database busy or locked
+/// are retried after a short pause.
+///
+/// `connection` is only used while holding the guard returned by `lock`, so that it can serve
+/// other commands between the steps. Changes it makes meanwhile are copied as well.
+#[cfg(feature = "backup")]
+pub fn execute_backup<L, G, F>(
+    connection: &Connection,
+    destination: &mut Connection,
+    pages_per_step: i32,
+    lock: L,
+    emit: F,
+) -> Result<()>
+where
+    L: Fn() -> G,
+    F: FnMut(i32, i32) -> Result<()>,
+{
+    let backup = {
+        let _guard = lock();
+        Backup::new(connection, destination).map_err(|error| Error::Database(error.to_string()))?
+    };
+    let pages_per_step = if pages_per_step > 0 {
+        pages_per_step
+    } else {
+        -1
+    };
+    let result = run_backup(&backup, pages_per_step, &lock, emit);
+
+    let _guard = lock();
+    drop(backup);
+    result
+}
+
+#[cfg(feature = "backup")]
+fn run_backup<L, G, F>(
+    backup: &Backup<'_, '_>,
+    pages_per_step: i32,
+    lock: &L,
+    mut emit: F,
+) -> Result<()>
+where
+    L: Fn() -> G,
+    F: FnMut(i32, i32) -> Result<()>,
+{
+    loop {
+        let step = {
+            let _guard = lock();
+            backup.step(pages_per_step)
+        }
+        .map_err(|error| Error::Database(error.to_string()))?;
+        match step {
+            StepResult::Done | StepResult::More => {
+                let progress = backup.progress();
+                emit(progress.remaining, progress.pagecount)?;
+                if step == StepResult::Done {
+                    return Ok(());
+                }
+            }
+            StepResult::Busy | StepResult::Locked => thread::sleep(Duration::from_millis(100)),
+            _ => return Err(Error::Database("Unexpected backup step result".to_string())),
+        }
+    }
+}
+
+/// Replaces the main database of `connection` with the one of `source`, after checking that
+/// `source` passes `PRAGMA integrity_check` and that `connection` has no open transaction.
+#[cfg(feature = "backup")]
+pub fn execute_restore(connection: &mut Connection, source: &Connection) -> Result<()> {
+    if !connection.is_autocommit() {
+        return Err(Error::Database(
+            "Cannot restore a database with an open transaction".to_string(),
+        ));
+    }
+
+    let mut statement = source
+        .prepare("PRAGMA integrity_check")
+        .map_err(|error| Error::Database(error.to_string()))?;
+    let problems = statement
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|error| Error::Database(error.to_string()))?
+        .collect::<rusqlite::Result<Vec<String>>>()
+        .map_err(|error| Error::Database(error.to_string()))?;
+    if problems != ["ok"] {
+        return Err(Error::Database(format!(
+            "Integrity check failed: {}",
+            problems.join("; ")
+        )));
+    }
+
+    execute_backup(source, connection, -1, || (), |_, _| Ok(()))
+}
+
+#[cfg(not(feature = "backup"))]
+pub fn execute_backup<L, G, F>(
+    _connection: &Connection,
+    _destination: &mut Connection,
+    _pages_per_step: i32,
+    _lock: L,
+    _emit: F,
+) -> Result<()>
+where
+    L: Fn() -> G,
+    F: FnMut(i32, i32) -> Result<()>,
+{
+    require_feature(false, "backup")
+}
+
+#[cfg(not(feature = "backup"))]
+pub fn execute_restore(_connection: &mut Connection, _source: &Connection) -> Result<()> {
+    require_feature(false, "backup")
+}
+
+#[cfg(all(test, feature = "backup"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn execute_backup_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE test (id INTEGER PRIMARY KEY, text_value TEXT);
+                WITH RECURSIVE numbers(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM numbers WHERE n < 200)
+                INSERT INTO test (text_value) SELECT printf('%.500c', 'x') FROM numbers;",
+            )
+            .unwrap();
+        let mut destination = Connection::open_in_memory().unwrap();
+
+        let mut progress = Vec::new();
+        execute_backup(
+            &connection,
+            &mut destination,
+            10,
+            || (),
+            |remaining, page_count| {
+                progress.push((remaining, page_count));
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert!(progress.len() > 1);
+        assert_eq!(progress.last().unwrap().0, 0);
+        assert!(progress[0].0 > 0);
+        assert_eq!(progress[0].1, progress.last().unwrap().1);
+
+        let count: i64 = destination
+            .query_row("SELECT COUNT(*) FROM test", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 200);
+
+        let mut destination = Connection::open_in_memory().unwrap();
+        let mut steps = 0;
+        execute_backup(
+            &connection,
+            &mut destination,
+            0,
+            || (),
+            |remaining, _| {
+                steps += 1;
+                assert_eq!(remaining, 0);
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(steps, 1);
+    }
+
+    #[test]
+    fn execute_backup_write_between_steps_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE test (id INTEGER PRIMARY KEY, text_value TEXT);
+                WITH RECURSIVE numbers(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM numbers WHERE n < 200)
+                INSERT INTO test (text_value) SELECT printf('%.500c', 'x') FROM numbers;",
+            )
+            .unwrap();
+        let mut destination = Connection::open_in_memory().unwrap();
+
+        let locks = std::cell::Cell::new(0);
+        let mut steps = 0;
+        execute_backup(
+            &connection,
+            &mut destination,
+            10,
+            || locks.set(locks.get() + 1),
+            |_, _| {
+                steps += 1;
+                if steps == 1 {
+                    connection
+                        .execute("INSERT INTO test (text_value) VALUES ('written')", [])
+                        .unwrap();
+                }
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert_eq!(locks.get(), steps + 2);
+        let count: i64 = destination
+            .query_row("SELECT COUNT(*) FROM test", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 201);
+    }
+
+    #[test]
+    fn execute_restore_test() {
+        let source = Connection::open_in_memory().unwrap();
+        source
+            .execute_batch(
+                "CREATE TABLE test (id INTEGER PRIMARY KEY, text_value TEXT);
+                INSERT INTO test (text_value) VALUES ('backup');",
+            )
+            .unwrap();
+        let mut connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE other (id INTEGER PRIMARY KEY);
+                BEGIN;",
+            )
+            .unwrap();
+
+        assert!(execute_restore(&mut connection, &source).is_err());
+
+        connection.execute_batch("COMMIT;").unwrap();
+        execute_restore(&mut connection, &source).unwrap();
+
+        let text_value: String = connection
+            .query_row("SELECT text_value FROM test", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(text_value, "backup");
+        assert!(connection.prepare("SELECT * FROM other").is_err());
+    }
+}
@@ -1,5 +1,7 @@
-use crate::{error::Error, types::Result};
-use rusqlite::Connection;
+use crate::{
+    commands::savepoint::with_savepoint, common::statement_length, error::Error, types::Result,
+};
+use rusqlite::{fallible_iterator::FallibleIterator, Batch, Connection};
 
 pub fn execute_batch(connection: &Connection, batch_sql: String) -> Result<()> {
     connection
@@ -8,6 +10,33 @@ pub fn execute_batch(connection: &Connection, batch_sql: String) -> Result<()> {
     Ok(())
 }
 
+/// Runs the batch inside a savepoint, rolled back if any statement fails, so that it also works
+/// inside an open transaction or savepoint. The error reports the index of the failing statement
+/// and its byte offset in the batch.
+pub fn execute_batch_in_transaction(connection: &Connection, batch_sql: String) -> Result<()> {
+    with_savepoint(connection, || {
+        let mut batch = Batch::new(connection, &batch_sql);
+        let mut index = 0;
+        let mut offset = 0;
+        loop {
+            let mut statement = match batch.next() {
+                Ok(Some(statement)) => statement,
+                Ok(None) => break,
+                Err(error) => return Err(Error::Script(index, offset, error.to_string())),
+            };
+            let length = statement_length(&mut statement)
+                .map_err(|error| Error::Script(index, offset, error.to_string()))?;
+
+            statement
+                .raw_execute()
+                .map_err(|error| Error::Script(index, offset, error.to_string()))?;
+            index += 1;
+            offset += length;
+        }
+        Ok(())
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -49,4 +78,77 @@ mod tests {
         let result = execute_batch(&connection, drop_table_sql);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn execute_batch_in_transaction_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        let batch_sql = r#"
+            CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+            INSERT INTO users (name) VALUES ('Alice');
+            "#
+        .to_string();
+        execute_batch_in_transaction(&connection, batch_sql).unwrap();
+
+        let count: i64 = connection
+            .query_row("SELECT count(*) FROM users", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn execute_batch_in_transaction_rollback_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);")
+            .unwrap();
+
+        let batch_sql = "INSERT INTO users (name) VALUES ('Alice'); INSERT INTO users (name) VALUES ('Bob'); INSERT INTO users (name) VALUES (NULL);".to_string();
+        let result = execute_batch_in_transaction(&connection, batch_sql.clone());
+
+        match result {
+            Err(Error::Script(index, offset, _)) => {
+                assert_eq!(index, 2);
+                assert_eq!(offset, batch_sql.rfind("; ").unwrap() + 1);
+            }
+            _ => panic!(),
+        }
+
+        let count: i64 = connection
+            .query_row("SELECT count(*) FROM users", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn execute_batch_in_transaction_in_savepoint_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);")
+            .unwrap();
+
+        connection.execute_batch("BEGIN").unwrap();
+        execute_batch_in_transaction(
+            &connection,
+            "INSERT INTO users (name) VALUES ('Alice');".to_string(),
+        )
+        .unwrap();
+        let result = execute_batch_in_transaction(
+            &connection,
+            "INSERT INTO users (name) VALUES ('Bob'); INSERT INTO users (name) VALUES (NULL);"
+                .to_string(),
+        );
+        assert!(matches!(result, Err(Error::Script(1, _, _))));
+        assert!(!connection.is_autocommit());
+
+        let count: i64 = connection
+            .query_row("SELECT count(*) FROM users", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+
+        connection.execute_batch("ROLLBACK").unwrap();
+        let count: i64 = connection
+            .query_row("SELECT count(*) FROM users", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+    }
 }
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+use rusqlite::Rows;
+
+use serde_json::Value as JsonValue;
+
+use crate::commands::select::convert_row;
+use crate::common::{bind_parameters, get_column_names, OwnedStatement, SharedConnection};
+use crate::error::Error;
+use crate::types::{Result, ResultList};
+
+/// A select whose statement stays open between fetches, so each fetch continues stepping where
+/// the previous one stopped. While rows remain, the statement keeps its read transaction open.
+pub struct Cursor {
+    pub name: String,
+    column_names: Vec<String>,
+    // Declared before `_statement` so that the rows borrowing it are dropped first.
+    rows: Option<Rows<'static>>,
+    _statement: OwnedStatement,
+}
+
+// SAFETY: see `SharedConnection`, whose lock also guards the cursors opened on it.
+unsafe impl Send for Cursor {}
+
+pub fn create_cursor(
+    connection: &SharedConnection,
+    name: String,
+    sql: String,
+    parameters: HashMap<String, JsonValue>,
+) -> Result<Cursor> {
+    let mut statement = OwnedStatement::new(connection, &sql)?;
+    // SAFETY: the rows are stored next to the statement in the cursor and dropped before it.
+    let statement_ref = unsafe { statement.statement() };
+    bind_parameters(statement_ref, &parameters)?;
+    let column_names = get_column_names(statement_ref);
+    let rows = statement_ref.raw_query();
+
+    Ok(Cursor {
+        name,
+        column_names,
+        rows: Some(rows),
+        _statement: statement,
+    })
+}
+
+/// Returns up to `count` rows following the ones already fetched from the cursor. Once the rows
+/// are exhausted the statement is reset, ending its read transaction.
+pub fn fetch_cursor(cursor: &mut Cursor, count: usize) -> Result<ResultList> {
+    let mut result = ResultList::new();
+    let rows = match cursor.rows.as_mut() {
+        Some(rows) => rows,
+        None => return Ok(result),
+    };
+
+    while result.len() < count {
+        match rows.next() {
+            Ok(Some(row)) => result.push(convert_row(row, &cursor.column_names)?),
+            Ok(None) => {
+                cursor.rows = None;
+                break;
+            }
+            Err(error) => {
+                cursor.rows = None;
+                return Err(Error::Database(error.to_string()));
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use super::*;
+
+    #[test]
+    fn fetch_cursor_test() {
+        let connection = SharedConnection::new(Connection::open_in_memory().unwrap());
+        connection
+            .execute_batch(
+                "CREATE TABLE test (id INTEGER PRIMARY KEY, text_value TEXT);
+                INSERT INTO test (text_value) VALUES ('test1'), ('test2'), ('test3'), ('test4'), ('test5');",
+            )
+            .unwrap();
+
+        let sql = "SELECT * FROM test WHERE id > :id ORDER BY id";
+
+        let mut parameters = HashMap::new();
+        parameters.insert(":id".to_string(), JsonValue::Number(1.into()));
+        let mut cursor =
+            create_cursor(&connection, "test".to_string(), sql.to_string(), parameters).unwrap();
+
+        let page = fetch_cursor(&mut cursor, 3).unwrap();
+        assert_eq!(page.len(), 3);
+        assert_eq!(page[0].get("id").unwrap(), &JsonValue::Number(2.into()));
+        assert_eq!(page[2].get("id").unwrap(), &JsonValue::Number(4.into()));
+
+        let page = fetch_cursor(&mut cursor, 3).unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(
+            page[0].get("text_value").unwrap(),
+            &JsonValue::String("test5".to_string())
+        );
+
+        let page = fetch_cursor(&mut cursor, 3).unwrap();
+        assert!(page.is_empty());
+        assert!(cursor.rows.is_none());
+    }
+
+    #[test]
+    fn fetch_cursor_write_between_fetches_test() {
+        let connection = SharedConnection::new(Connection::open_in_memory().unwrap());
+        connection
+            .execute_batch(
+                "CREATE TABLE test (id INTEGER PRIMARY KEY, text_value TEXT);
+                INSERT INTO test (text_value) VALUES ('test1'), ('test2'), ('test3'), ('test4');",
+            )
+            .unwrap();
+
+        let mut cursor = create_cursor(
+            &connection,
+            "test".to_string(),
+            "SELECT * FROM test ORDER BY id".to_string(),
+            HashMap::new(),
+        )
+        .unwrap();
+
+        let page = fetch_cursor(&mut cursor, 2).unwrap();
+        assert_eq!(page[1].get("id").unwrap(), &JsonValue::Number(2.into()));
+
+        connection
+            .execute("DELETE FROM test WHERE id <= 2", [])
+            .unwrap();
+
+        let page = fetch_cursor(&mut cursor, 2).unwrap();
+        assert_eq!(page.len(), 2);
+        assert_eq!(page[0].get("id").unwrap(), &JsonValue::Number(3.into()));
+        assert_eq!(page[1].get("id").unwrap(), &JsonValue::Number(4.into()));
+
+        drop(cursor);
+        assert!(connection.into_inner().unwrap().close().is_ok());
+    }
+
+    #[test]
+    fn create_cursor_invalid_sql_test() {
+        let connection = SharedConnection::new(Connection::open_in_memory().unwrap());
+        let result = create_cursor(
+            &connection,
+            "test".to_string(),
+            "SELECT * FROM missing".to_string(),
+            HashMap::new(),
+        );
+        assert!(result.is_err());
+    }
+}
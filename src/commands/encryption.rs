@@ -0,0 +1,79 @@
+use rusqlite::Connection;
+
+#[cfg(not(feature = "sqlcipher"))]
+use crate::common::require_feature;
+#[cfg(feature = "sqlcipher")]
+use crate::error::Error;
+use crate::types::Result;
+
+/// Sets the key of a database encrypted with SQLCipher. It must be the first statement run on
+/// the connection, and a wrong key is only detected once the database is read.
+#[cfg(feature = "sqlcipher")]
+pub fn apply_key(connection: &Connection, key: &str) -> Result<()> {
+    connection
+        .pragma_update(None, "key", key)
+        .map_err(|error| Error::Encryption(error.to_string()))?;
+    connection
+        .query_row("SELECT COUNT(*) FROM sqlite_schema", [], |_| Ok(()))
+        .map_err(|error| Error::Encryption(error.to_string()))
+}
+
+/// Changes the key of a database encrypted with SQLCipher, encrypting it if it had no key.
+#[cfg(feature = "sqlcipher")]
+pub fn execute_rekey(connection: &Connection, key: &str) -> Result<()> {
+    connection
+        .pragma_update(None, "rekey", key)
+        .map_err(|error| Error::Encryption(error.to_string()))
+}
+
+/// Plain SQLite ignores the key pragmas, which would leave the database unencrypted.
+#[cfg(not(feature = "sqlcipher"))]
+pub fn apply_key(_connection: &Connection, _key: &str) -> Result<()> {
+    require_feature(false, "sqlcipher")
+}
+
+#[cfg(not(feature = "sqlcipher"))]
+pub fn execute_rekey(_connection: &Connection, _key: &str) -> Result<()> {
+    require_feature(false, "sqlcipher")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(feature = "sqlcipher"))]
+    #[test]
+    fn apply_key_unsupported_test() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        assert!(apply_key(&connection, "secret").is_err());
+        assert!(execute_rekey(&connection, "secret").is_err());
+    }
+
+    #[cfg(feature = "sqlcipher")]
+    #[test]
+    fn apply_key_test() {
+        let path = std::env::temp_dir().join(format!("encryption_{}.db", std::process::id()));
+
+        let connection = Connection::open(&path).unwrap();
+        apply_key(&connection, "secret").unwrap();
+        connection
+            .execute_batch("CREATE TABLE test (id INTEGER PRIMARY KEY);")
+            .unwrap();
+        execute_rekey(&connection, "rotated").unwrap();
+        drop(connection);
+
+        let connection = Connection::open(&path).unwrap();
+        assert!(apply_key(&connection, "secret").is_err());
+        drop(connection);
+
+        let connection = Connection::open(&path).unwrap();
+        apply_key(&connection, "rotated").unwrap();
+        connection
+            .execute("INSERT INTO test DEFAULT VALUES", [])
+            .unwrap();
+        drop(connection);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,518 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "hooks")]
+use rusqlite::hooks::Action;
+use rusqlite::Connection;
+
+use crate::types::{Change, TransactionChanges};
+
+/// Tables of a connection whose changes are reported.
+pub type Subscriptions = Arc<Mutex<HashSet<String>>>;
+
+/// Changes of a connection waiting for their transaction to end.
+pub type Pending = Arc<Mutex<PendingChanges>>;
+
+/// The state shared between the hooks of a connection and the commands using it.
+#[derive(Clone, Default)]
+pub struct Hooks {
+    pub subscriptions: Subscriptions,
+    pub pending: Pending,
+}
+
+#[cfg_attr(not(feature = "hooks"), allow(dead_code))]
+pub enum HookEvent {
+    Change(Change),
+    /// The commit hook ran. It runs before the commit completes, which may still fail, so the
+    /// changes are reported by [`PendingChanges::settle`] once the command has returned.
+    Committing,
+    Rollback(TransactionChanges),
+}
+
+/// Changes and modified tables, numbered in the order they were recorded.
+#[derive(Default)]
+struct Changes {
+    changes: Vec<(u64, Change)>,
+    /// Tables modified, subscribed or not, with the number of the first change to each.
+    tables: HashMap<String, u64>,
+}
+
+impl Changes {
+    fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    fn append(&mut self, other: Changes) {
+        self.changes.extend(other.changes);
+        for (table, number) in other.tables {
+            self.tables.entry(table).or_insert(number);
+        }
+    }
+
+    fn discard_since(&mut self, mark: u64) {
+        self.changes.retain(|(number, _)| *number < mark);
+        self.tables.retain(|_, number| *number < mark);
+    }
+
+    fn into_transaction(self, name: &str) -> TransactionChanges {
+        let mut tables = self.tables.into_iter().collect::<Vec<_>>();
+        tables.sort_by_key(|(_, number)| *number);
+        TransactionChanges {
+            name: name.to_string(),
+            tables: tables.into_iter().map(|(table, _)| table).collect(),
+            changes: self.changes.into_iter().map(|(_, change)| change).collect(),
+        }
+    }
+}
+
+/// Changes recorded by the hooks of a connection until its transaction ends.
+#[derive(Default)]
+pub struct PendingChanges {
+    /// Number of changes recorded so far, used to number the next one.
+    recorded: u64,
+    current: Changes,
+    /// Changes of the transactions committed since the connection was last settled.
+    committed: Changes,
+    /// Changes of the transaction whose commit hook ran last, when nothing was recorded since,
+    /// as the commit may still fail.
+    committing: Option<Changes>,
+    /// Savepoints opened through the plugin, with the number of the first change they hold.
+    savepoints: Vec<(String, u64)>,
+}
+
+impl PendingChanges {
+    /// Returns the number of the next change, to forget the changes recorded after it with
+    /// [`PendingChanges::discard_since`].
+    pub fn mark(&self) -> u64 {
+        self.recorded
+    }
+
+    /// Forgets the changes recorded since `mark`, which were rolled back without the rollback
+    /// hook running, as done by `ROLLBACK TO`.
+    pub fn discard_since(&mut self, mark: u64) {
+        self.current.discard_since(mark);
+        if let Some(committing) = &mut self.committing {
+            committing.discard_since(mark);
+        }
+    }
+
+    pub fn savepoint(&mut self, savepoint: &str) {
+        self.savepoints.push((savepoint.to_string(), self.recorded));
+    }
+
+    /// Closes the savepoint and every savepoint opened after it. Names compare like SQLite
+    /// does, ignoring ASCII case.
+    pub fn release(&mut self, savepoint: &str) {
+        if let Some(index) = self.find_savepoint(savepoint) {
+            self.savepoints.truncate(index);
+        }
+    }
+
+    /// Forgets the changes made since the savepoint was opened, which stays open.
+    pub fn rollback_to(&mut self, savepoint: &str) {
+        if let Some(index) = self.find_savepoint(savepoint) {
+            let mark = self.savepoints[index].1;
+            self.savepoints.truncate(index + 1);
+            self.discard_since(mark);
+        }
+    }
+
+    /// Returns the changes committed by the command that has just returned, before the connection
+    /// runs anything else. The last commit succeeded if the connection is back in autocommit
+    /// mode. Otherwise it failed, for instance because the database was busy, and its changes
+    /// belong to the transaction still open, unless the command opened a new transaction after
+    /// committing, which cannot be told apart.
+    pub fn settle(&mut self, connection: &Connection, name: &str) -> Option<TransactionChanges> {
+        if let Some(committing) = self.committing.take() {
+            if connection.is_autocommit() {
+                self.committed.append(committing);
+            } else {
+                let current = std::mem::replace(&mut self.current, committing);
+                self.current.append(current);
+            }
+        }
+        let committed = std::mem::take(&mut self.committed);
+        (!committed.is_empty()).then(|| committed.into_transaction(name))
+    }
+
+    fn find_savepoint(&self, savepoint: &str) -> Option<usize> {
+        self.savepoints
+            .iter()
+            .rposition(|(name, _)| name.eq_ignore_ascii_case(savepoint))
+    }
+
+    /// Takes the last commit as complete, since a change was recorded after it.
+    #[cfg(feature = "hooks")]
+    fn complete_commit(&mut self) {
+        if let Some(committing) = self.committing.take() {
+            self.committed.append(committing);
+        }
+    }
+
+    #[cfg(feature = "hooks")]
+    fn record(&mut self, table: &str, change: Option<Change>) {
+        self.complete_commit();
+        let number = self.recorded;
+        self.recorded += 1;
+        self.current
+            .tables
+            .entry(table.to_string())
+            .or_insert(number);
+        if let Some(change) = change {
+            self.current.changes.push((number, change));
+        }
+    }
+
+    /// A commit with nothing recorded since the previous one retries it or changes nothing that
+    /// is reported, so both end up in the same transaction.
+    #[cfg(feature = "hooks")]
+    fn commit(&mut self) {
+        let current = std::mem::take(&mut self.current);
+        match &mut self.committing {
+            Some(committing) => committing.append(current),
+            None => self.committing = Some(current),
+        }
+        self.savepoints.clear();
+    }
+
+    /// Returns the changes rolled back: those of the open transaction, along with the last commit
+    /// when nothing was recorded since, as a statement whose commit fails is rolled back. Commits
+    /// known to be complete are never included.
+    #[cfg(feature = "hooks")]
+    fn rollback(&mut self) -> Changes {
+        let mut changes = self.committing.take().unwrap_or_default();
+        changes.append(std::mem::take(&mut self.current));
+        self.savepoints.clear();
+        changes
+    }
+}
+
+/// Registers the update, commit and rollback hooks of the connection. Every row inserted, updated
+/// or deleted in a subscribed table is reported as a [`HookEvent::Change`] and collected until
+/// the transaction ends, so that the commit or the rollback reports all of them at once, along
+/// with the name of every table modified, subscribed or not. Outside of an explicit transaction
+/// every statement commits on its own. Transactions without changes are not reported.
+///
+/// SQLite runs no hook for `ROLLBACK TO`, so the changes it undoes are only forgotten when the
+/// savepoint is handled by [`PendingChanges`], as the savepoint commands of the plugin do.
+///
+/// Changes to `WITHOUT ROWID` tables are not reported by SQLite.
+#[cfg(feature = "hooks")]
+pub fn register_hooks<F>(connection: &Connection, name: String, hooks: &Hooks, emit: F)
+where
+    F: Fn(HookEvent) + Send + Sync + 'static,
+{
+    let emit = Arc::new(emit);
+
+    let update_emit = emit.clone();
+    let update_pending = hooks.pending.clone();
+    let subscriptions = hooks.subscriptions.clone();
+    let update_name = name.clone();
+    connection.update_hook(Some(
+        move |action: Action, database: &str, table: &str, rowid: i64| {
+            let operation = match action {
+                Action::SQLITE_INSERT => "insert",
+                Action::SQLITE_UPDATE => "update",
+                Action::SQLITE_DELETE => "delete",
+                _ => return,
+            };
+            if !subscriptions.lock().unwrap().contains(table) {
+                update_pending.lock().unwrap().record(table, None);
+                return;
+            }
+
+            let change = Change {
+                name: update_name.clone(),
+                database: database.to_string(),
+                table: table.to_string(),
+                operation: operation.to_string(),
+                rowid,
+            };
+            update_pending
+                .lock()
+                .unwrap()
+                .record(table, Some(change.clone()));
+            update_emit(HookEvent::Change(change));
+        },
+    ));
+
+    let commit_emit = emit.clone();
+    let commit_pending = hooks.pending.clone();
+    connection.commit_hook(Some(move || {
+        commit_pending.lock().unwrap().commit();
+        commit_emit(HookEvent::Committing);
+        false
+    }));
+
+    let rollback_pending = hooks.pending.clone();
+    connection.rollback_hook(Some(move || {
+        let changes = rollback_pending.lock().unwrap().rollback();
+        if !changes.is_empty() {
+            emit(HookEvent::Rollback(changes.into_transaction(&name)));
+        }
+    }));
+}
+
+/// Without the hooks feature no event is ever reported.
+#[cfg(not(feature = "hooks"))]
+pub fn register_hooks<F>(_connection: &Connection, _name: String, _hooks: &Hooks, _emit: F)
+where
+    F: Fn(HookEvent) + Send + Sync + 'static,
+{
+}
+
+#[cfg(all(test, feature = "hooks"))]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    fn create_connection() -> (Connection, Hooks, mpsc::Receiver<HookEvent>) {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+                CREATE TABLE logs (id INTEGER PRIMARY KEY, message TEXT NOT NULL);",
+            )
+            .unwrap();
+
+        let hooks = Hooks::default();
+        hooks
+            .subscriptions
+            .lock()
+            .unwrap()
+            .insert("users".to_string());
+
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        register_hooks(&connection, "test".to_string(), &hooks, move |event| {
+            sender.lock().unwrap().send(event).unwrap()
+        });
+
+        (connection, hooks, receiver)
+    }
+
+    /// Settles the commits signalled since the last call, like the plugin does once a command
+    /// has returned.
+    fn take_commits(
+        connection: &Connection,
+        hooks: &Hooks,
+        receiver: &mpsc::Receiver<HookEvent>,
+    ) -> Vec<TransactionChanges> {
+        let committing = receiver
+            .try_iter()
+            .filter(|event| matches!(event, HookEvent::Committing))
+            .count();
+        if committing == 0 {
+            return Vec::new();
+        }
+        hooks
+            .pending
+            .lock()
+            .unwrap()
+            .settle(connection, "test")
+            .into_iter()
+            .collect()
+    }
+
+    fn rollbacks(receiver: &mpsc::Receiver<HookEvent>) -> Vec<TransactionChanges> {
+        receiver
+            .try_iter()
+            .filter_map(|event| match event {
+                HookEvent::Rollback(rollback) => Some(rollback),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn register_hooks_change_test() {
+        let (connection, hooks, receiver) = create_connection();
+
+        connection
+            .execute_batch(
+                "INSERT INTO users (name) VALUES ('Alice');
+                INSERT INTO logs (message) VALUES ('created');
+                UPDATE users SET name = 'Bob' WHERE id = 1;
+                DELETE FROM users WHERE id = 1;",
+            )
+            .unwrap();
+
+        let changes = receiver
+            .try_iter()
+            .filter_map(|event| match event {
+                HookEvent::Change(change) => Some(change),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes
+                .iter()
+                .map(|change| change.operation.as_str())
+                .collect::<Vec<_>>(),
+            vec!["insert", "update", "delete"]
+        );
+        assert_eq!(changes[0].name, "test");
+        assert_eq!(changes[0].database, "main");
+        assert_eq!(changes[0].table, "users");
+        assert_eq!(changes[0].rowid, 1);
+
+        hooks.subscriptions.lock().unwrap().clear();
+        connection
+            .execute_batch("INSERT INTO users (name) VALUES ('Carol');")
+            .unwrap();
+        assert!(!receiver
+            .try_iter()
+            .any(|event| matches!(event, HookEvent::Change(_))));
+    }
+
+    #[test]
+    fn register_hooks_transaction_test() {
+        let (connection, hooks, receiver) = create_connection();
+
+        connection
+            .execute_batch(
+                "BEGIN;
+                INSERT INTO users (name) VALUES ('Alice');
+                INSERT INTO users (name) VALUES ('Bob');
+                COMMIT;",
+            )
+            .unwrap();
+
+        let commits = take_commits(&connection, &hooks, &receiver);
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].name, "test");
+        assert_eq!(commits[0].tables, vec!["users".to_string()]);
+        assert_eq!(commits[0].changes.len(), 2);
+
+        connection
+            .execute_batch(
+                "BEGIN;
+                DELETE FROM users WHERE id > 0;
+                ROLLBACK;",
+            )
+            .unwrap();
+
+        let events = receiver.try_iter().collect::<Vec<_>>();
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, HookEvent::Committing)));
+        match events.last() {
+            Some(HookEvent::Rollback(rollback)) => assert_eq!(rollback.changes.len(), 2),
+            _ => panic!(),
+        }
+
+        connection
+            .execute_batch("INSERT INTO users (name) VALUES ('Carol');")
+            .unwrap();
+        let commits = take_commits(&connection, &hooks, &receiver);
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].changes.len(), 1);
+
+        connection
+            .execute_batch("INSERT INTO logs (message) VALUES ('created');")
+            .unwrap();
+        let commits = take_commits(&connection, &hooks, &receiver);
+        assert_eq!(commits[0].tables, vec!["logs".to_string()]);
+        assert!(commits[0].changes.is_empty());
+
+        connection
+            .execute_batch("BEGIN; SELECT * FROM users; COMMIT;")
+            .unwrap();
+        assert!(take_commits(&connection, &hooks, &receiver).is_empty());
+    }
+
+    #[test]
+    fn register_hooks_unconfirmed_commit_test() {
+        let (connection, hooks, receiver) = create_connection();
+
+        connection
+            .execute_batch("BEGIN; INSERT INTO users (name) VALUES ('Alice'); COMMIT; BEGIN;")
+            .unwrap();
+        assert!(take_commits(&connection, &hooks, &receiver).is_empty());
+
+        connection
+            .execute_batch("INSERT INTO users (name) VALUES ('Bob'); COMMIT;")
+            .unwrap();
+        let commits = take_commits(&connection, &hooks, &receiver);
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].changes.len(), 2);
+    }
+
+    #[test]
+    fn register_hooks_commit_then_transaction_test() {
+        let (connection, hooks, receiver) = create_connection();
+
+        connection
+            .execute_batch("INSERT INTO users (name) VALUES ('Alice');")
+            .unwrap();
+        let commits = take_commits(&connection, &hooks, &receiver);
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].changes.len(), 1);
+
+        connection.execute_batch("BEGIN;").unwrap();
+        assert!(take_commits(&connection, &hooks, &receiver).is_empty());
+        connection.execute_batch("ROLLBACK;").unwrap();
+        assert!(rollbacks(&receiver).is_empty());
+
+        connection
+            .execute_batch(
+                "INSERT INTO users (name) VALUES ('Bob');
+                BEGIN;
+                INSERT INTO users (name) VALUES ('Carol');
+                ROLLBACK;",
+            )
+            .unwrap();
+        let rollbacks = rollbacks(&receiver);
+        assert_eq!(rollbacks.len(), 1);
+        assert_eq!(rollbacks[0].changes.len(), 1);
+        assert_eq!(rollbacks[0].changes[0].rowid, 3);
+
+        let commits = hooks
+            .pending
+            .lock()
+            .unwrap()
+            .settle(&connection, "test")
+            .unwrap();
+        assert_eq!(commits.changes.len(), 1);
+        assert_eq!(commits.changes[0].rowid, 2);
+    }
+
+    #[test]
+    fn register_hooks_savepoint_test() {
+        let (connection, hooks, receiver) = create_connection();
+
+        connection
+            .execute_batch("BEGIN; INSERT INTO users (name) VALUES ('Alice');")
+            .unwrap();
+        connection.execute_batch("SAVEPOINT first").unwrap();
+        hooks.pending.lock().unwrap().savepoint("first");
+        connection
+            .execute_batch("INSERT INTO logs (message) VALUES ('created');")
+            .unwrap();
+        connection.execute_batch("SAVEPOINT second").unwrap();
+        hooks.pending.lock().unwrap().savepoint("second");
+        connection
+            .execute_batch("INSERT INTO users (name) VALUES ('Bob');")
+            .unwrap();
+
+        connection.execute_batch("ROLLBACK TO FIRST").unwrap();
+        hooks.pending.lock().unwrap().rollback_to("FIRST");
+        connection
+            .execute_batch("INSERT INTO users (name) VALUES ('Carol'); RELEASE first; COMMIT;")
+            .unwrap();
+        hooks.pending.lock().unwrap().release("first");
+
+        let commits = take_commits(&connection, &hooks, &receiver);
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].tables, vec!["users".to_string()]);
+        assert_eq!(
+            commits[0]
+                .changes
+                .iter()
+                .map(|change| change.rowid)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+    }
+}
@@ -0,0 +1,293 @@
+use rusqlite::{params, Connection};
+
+use crate::common::quote_identifier;
+use crate::error::Error;
+use crate::types::{ForeignKey, Result, SchemaObject, TableColumn, TableIndex};
+
+/// Returns the tables of `schema`, `main` for the database itself or the alias of an attached
+/// database, without the internal `sqlite_` tables.
+pub fn list_tables(connection: &Connection, schema: &str) -> Result<Vec<SchemaObject>> {
+    list_objects(connection, schema, "table")
+}
+
+pub fn list_views(connection: &Connection, schema: &str) -> Result<Vec<SchemaObject>> {
+    list_objects(connection, schema, "view")
+}
+
+pub fn list_triggers(connection: &Connection, schema: &str) -> Result<Vec<SchemaObject>> {
+    list_objects(connection, schema, "trigger")
+}
+
+fn list_objects(
+    connection: &Connection,
+    schema: &str,
+    object_type: &str,
+) -> Result<Vec<SchemaObject>> {
+    let sql = format!(
+        "SELECT name, tbl_name, sql FROM {}.sqlite_schema
+        WHERE type = ?1 AND name NOT LIKE 'sqlite\\_%' ESCAPE '\\' ORDER BY name",
+        quote_identifier(schema)
+    );
+    let mut statement = connection
+        .prepare(&sql)
+        .map_err(|error| Error::Database(error.to_string()))?;
+    let objects = statement
+        .query_map([object_type], |row| {
+            Ok(SchemaObject {
+                name: row.get(0)?,
+                table_name: row.get(1)?,
+                sql: row.get(2)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|error| Error::Database(error.to_string()))?;
+    Ok(objects)
+}
+
+/// Returns the columns of a table or view in their declaration order.
+pub fn list_columns(
+    connection: &Connection,
+    schema: &str,
+    table: &str,
+) -> Result<Vec<TableColumn>> {
+    let mut statement = connection
+        .prepare(
+            "SELECT name, type, \"notnull\", dflt_value, pk FROM pragma_table_info(?1, ?2)
+            ORDER BY cid",
+        )
+        .map_err(|error| Error::Database(error.to_string()))?;
+    let columns = statement
+        .query_map([table, schema], |row| {
+            let decl_type: String = row.get(1)?;
+            Ok(TableColumn {
+                name: row.get(0)?,
+                decl_type: Some(decl_type).filter(|decl_type| !decl_type.is_empty()),
+                not_null: row.get(2)?,
+                default_value: row.get(3)?,
+                primary_key: row.get(4)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|error| Error::Database(error.to_string()))?;
+    Ok(columns)
+}
+
+/// Returns the indexes of a table with their columns. Columns of indexes on expressions are
+/// `None`.
+pub fn list_indexes(connection: &Connection, schema: &str, table: &str) -> Result<Vec<TableIndex>> {
+    let mut statement = connection
+        .prepare(
+            "SELECT name, \"unique\", origin, partial FROM pragma_index_list(?1, ?2) ORDER BY name",
+        )
+        .map_err(|error| Error::Database(error.to_string()))?;
+    let mut indexes = statement
+        .query_map([table, schema], |row| {
+            Ok(TableIndex {
+                name: row.get(0)?,
+                unique: row.get(1)?,
+                origin: row.get(2)?,
+                partial: row.get(3)?,
+                columns: Vec::new(),
+            })
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|error| Error::Database(error.to_string()))?;
+
+    let mut statement = connection
+        .prepare("SELECT name FROM pragma_index_info(?1, ?2) ORDER BY seqno")
+        .map_err(|error| Error::Database(error.to_string()))?;
+    for index in indexes.iter_mut() {
+        index.columns = statement
+            .query_map(params![index.name, schema], |row| row.get(0))
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|error| Error::Database(error.to_string()))?;
+    }
+    Ok(indexes)
+}
+
+/// Returns the foreign keys of a table, with the columns of composite keys grouped together.
+pub fn list_foreign_keys(
+    connection: &Connection,
+    schema: &str,
+    table: &str,
+) -> Result<Vec<ForeignKey>> {
+    let mut statement = connection
+        .prepare(
+            "SELECT id, \"table\", \"from\", \"to\", on_update, on_delete
+            FROM pragma_foreign_key_list(?1, ?2) ORDER BY id, seq",
+        )
+        .map_err(|error| Error::Database(error.to_string()))?;
+    let rows = statement
+        .query_map([table, schema], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, String>(5)?,
+            ))
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|error| Error::Database(error.to_string()))?;
+
+    let mut foreign_keys = Vec::<ForeignKey>::new();
+    for (id, table, from, to, on_update, on_delete) in rows {
+        match foreign_keys.last_mut() {
+            Some(foreign_key) if foreign_key.id == id => {
+                foreign_key.from.push(from);
+                foreign_key.to.push(to);
+            }
+            _ => foreign_keys.push(ForeignKey {
+                id,
+                table,
+                from: vec![from],
+                to: vec![to],
+                on_update,
+                on_delete,
+            }),
+        }
+    }
+    Ok(foreign_keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_database() -> Connection {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL DEFAULT 'anonymous');
+                CREATE TABLE groups (owner_id INTEGER, code TEXT, UNIQUE (owner_id, code));
+                CREATE TABLE memberships (
+                    user_id INTEGER REFERENCES users ON DELETE CASCADE,
+                    owner_id INTEGER,
+                    code TEXT,
+                    FOREIGN KEY (owner_id, code) REFERENCES groups (owner_id, code)
+                );
+                CREATE INDEX memberships_user ON memberships (user_id) WHERE user_id > 0;
+                CREATE INDEX users_lower_name ON users (lower(name));
+                CREATE VIEW user_names AS SELECT name FROM users;
+                CREATE TRIGGER users_delete AFTER DELETE ON users BEGIN SELECT 1; END;",
+            )
+            .unwrap();
+        connection
+    }
+
+    #[test]
+    fn list_objects_test() {
+        let connection = create_database();
+
+        let tables = list_tables(&connection, "main").unwrap();
+        assert_eq!(
+            tables
+                .iter()
+                .map(|table| table.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["groups", "memberships", "users"]
+        );
+
+        let views = list_views(&connection, "main").unwrap();
+        assert_eq!(views.len(), 1);
+        assert_eq!(views[0].name, "user_names");
+
+        let triggers = list_triggers(&connection, "main").unwrap();
+        assert_eq!(triggers.len(), 1);
+        assert_eq!(triggers[0].table_name, "users");
+        assert!(triggers[0]
+            .sql
+            .as_deref()
+            .unwrap()
+            .starts_with("CREATE TRIGGER"));
+
+        connection
+            .execute_batch("ATTACH DATABASE ':memory:' AS other; CREATE TABLE other.logs (id);")
+            .unwrap();
+        let tables = list_tables(&connection, "other").unwrap();
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].name, "logs");
+        assert!(list_tables(&connection, "missing").is_err());
+    }
+
+    #[test]
+    fn list_columns_test() {
+        let connection = create_database();
+
+        let columns = list_columns(&connection, "main", "users").unwrap();
+        assert_eq!(
+            columns,
+            vec![
+                TableColumn {
+                    name: "id".to_string(),
+                    decl_type: Some("INTEGER".to_string()),
+                    not_null: false,
+                    default_value: None,
+                    primary_key: 1,
+                },
+                TableColumn {
+                    name: "name".to_string(),
+                    decl_type: Some("TEXT".to_string()),
+                    not_null: true,
+                    default_value: Some("'anonymous'".to_string()),
+                    primary_key: 0,
+                },
+            ]
+        );
+        assert!(list_columns(&connection, "main", "missing")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn list_indexes_test() {
+        let connection = create_database();
+
+        let indexes = list_indexes(&connection, "main", "memberships").unwrap();
+        assert_eq!(indexes.len(), 1);
+        assert_eq!(indexes[0].name, "memberships_user");
+        assert!(indexes[0].partial);
+        assert_eq!(indexes[0].origin, "c");
+        assert_eq!(indexes[0].columns, vec![Some("user_id".to_string())]);
+
+        let indexes = list_indexes(&connection, "main", "groups").unwrap();
+        assert_eq!(indexes.len(), 1);
+        assert!(indexes[0].unique);
+        assert_eq!(indexes[0].origin, "u");
+        assert_eq!(
+            indexes[0].columns,
+            vec![Some("owner_id".to_string()), Some("code".to_string())]
+        );
+
+        let indexes = list_indexes(&connection, "main", "users").unwrap();
+        assert_eq!(indexes[0].columns, vec![None]);
+    }
+
+    #[test]
+    fn list_foreign_keys_test() {
+        let connection = create_database();
+
+        let foreign_keys = list_foreign_keys(&connection, "main", "memberships").unwrap();
+        assert_eq!(foreign_keys.len(), 2);
+
+        let groups = foreign_keys
+            .iter()
+            .find(|foreign_key| foreign_key.table == "groups")
+            .unwrap();
+        assert_eq!(groups.from, vec!["owner_id", "code"]);
+        assert_eq!(
+            groups.to,
+            vec![Some("owner_id".to_string()), Some("code".to_string())]
+        );
+
+        let users = foreign_keys
+            .iter()
+            .find(|foreign_key| foreign_key.table == "users")
+            .unwrap();
+        assert_eq!(users.from, vec!["user_id"]);
+        assert_eq!(users.to, vec![None]);
+        assert_eq!(users.on_delete, "CASCADE");
+        assert_eq!(users.on_update, "NO ACTION");
+    }
+}
@@ -1,4 +1,17 @@
+pub mod attach;
+pub mod backup;
 pub mod batch;
+pub mod cursor;
+pub mod encryption;
+pub mod hooks;
+pub mod introspection;
 pub mod migration;
+pub mod savepoint;
+pub mod script;
 pub mod select;
+pub mod serialize;
+pub mod statement;
+pub mod stream;
 pub mod update;
+pub mod vacuum;
+pub mod watch;
@@ -0,0 +1,90 @@
+use rusqlite::Connection;
+
+use crate::common::quote_identifier;
+use crate::error::Error;
+use crate::types::Result;
+
+/// Opens a savepoint. Outside of a transaction the savepoint starts one, committed when the
+/// outermost savepoint is released.
+pub fn execute_savepoint(connection: &Connection, savepoint: &str) -> Result<()> {
+    connection
+        .execute_batch(&format!("SAVEPOINT {}", quote_identifier(savepoint)))
+        .map_err(|error| Error::Database(error.to_string()))
+}
+
+/// Releases the savepoint and every savepoint opened after it, keeping their changes.
+pub fn execute_release(connection: &Connection, savepoint: &str) -> Result<()> {
+    connection
+        .execute_batch(&format!("RELEASE {}", quote_identifier(savepoint)))
+        .map_err(|error| Error::Database(error.to_string()))
+}
+
+/// Reverts the changes made since the savepoint was opened. The savepoint stays open and still has
+/// to be released.
+pub fn execute_rollback_to(connection: &Connection, savepoint: &str) -> Result<()> {
+    connection
+        .execute_batch(&format!("ROLLBACK TO {}", quote_identifier(savepoint)))
+        .map_err(|error| Error::Database(error.to_string()))
+}
+
+/// Runs `f` inside a savepoint, released when `f` succeeds and rolled back when it fails. Unlike
+/// a transaction, the savepoint nests inside an open transaction or savepoint.
+pub fn with_savepoint<T, F>(connection: &Connection, f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T>,
+{
+    const SAVEPOINT: &str = "tauri_plugin_rusqlite";
+
+    execute_savepoint(connection, SAVEPOINT)?;
+    let result = f().and_then(|value| execute_release(connection, SAVEPOINT).map(|_| value));
+    if result.is_err() {
+        let _ = execute_rollback_to(connection, SAVEPOINT)
+            .and_then(|_| execute_release(connection, SAVEPOINT));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_users(connection: &Connection) -> i64 {
+        connection
+            .query_row("SELECT count(*) FROM users", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn execute_nested_savepoints_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);")
+            .unwrap();
+
+        execute_savepoint(&connection, "outer").unwrap();
+        assert!(!connection.is_autocommit());
+        connection
+            .execute_batch("INSERT INTO users (name) VALUES ('Alice');")
+            .unwrap();
+
+        execute_savepoint(&connection, "inner unit").unwrap();
+        connection
+            .execute_batch("INSERT INTO users (name) VALUES ('Bob');")
+            .unwrap();
+        assert_eq!(count_users(&connection), 2);
+
+        execute_rollback_to(&connection, "inner unit").unwrap();
+        execute_release(&connection, "inner unit").unwrap();
+        assert_eq!(count_users(&connection), 1);
+
+        execute_release(&connection, "outer").unwrap();
+        assert!(connection.is_autocommit());
+        assert_eq!(count_users(&connection), 1);
+    }
+
+    #[test]
+    fn execute_release_unknown_savepoint_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        assert!(execute_release(&connection, "missing").is_err());
+    }
+}
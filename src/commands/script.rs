@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use rusqlite::{fallible_iterator::FallibleIterator, Batch, Connection, Statement};
+
+use serde_json::Value as JsonValue;
+
+use crate::commands::select::query_rows;
+use crate::common::{bind_parameters, statement_length};
+use crate::error::Error;
+use crate::types::{Result, StatementResult};
+
+/// Runs the statements of `script` one after the other, binding the parameter set with the same
+/// index to each statement. Statements returning columns produce their rows, the others the
+/// number of changes and the last inserted rowid.
+///
+/// When `continue_on_error` is set, a failing statement produces an error result and the script
+/// goes on with the next one. A statement that cannot be compiled always stops the script, as
+/// its end cannot be found.
+pub fn execute_script(
+    connection: &Connection,
+    script: String,
+    parameters_list: Vec<HashMap<String, JsonValue>>,
+    continue_on_error: bool,
+) -> Result<Vec<StatementResult>> {
+    let mut results = Vec::new();
+    let mut batch = Batch::new(connection, &script);
+    let mut offset = 0;
+
+    loop {
+        let mut statement = match batch.next() {
+            Ok(Some(statement)) => statement,
+            Ok(None) => break,
+            Err(error) => return Err(Error::Script(results.len(), offset, error.to_string())),
+        };
+        let length = statement_length(&mut statement)
+            .map_err(|error| Error::Script(results.len(), offset, error.to_string()))?;
+
+        let parameters = parameters_list
+            .get(results.len())
+            .cloned()
+            .unwrap_or_default();
+        match execute_statement(connection, &mut statement, &parameters) {
+            Ok(result) => results.push(result),
+            Err(error) if continue_on_error => results.push(StatementResult::Error {
+                offset,
+                error: error.to_string(),
+            }),
+            Err(error) => return Err(Error::Script(results.len(), offset, error.to_string())),
+        }
+        offset += length;
+    }
+
+    Ok(results)
+}
+
+fn execute_statement(
+    connection: &Connection,
+    statement: &mut Statement<'_>,
+    parameters: &HashMap<String, JsonValue>,
+) -> Result<StatementResult> {
+    if statement.column_count() > 0 {
+        let rows = query_rows(statement, parameters)?;
+        return Ok(StatementResult::Rows { rows });
+    }
+
+    bind_parameters(statement, parameters)?;
+    let changes = statement
+        .raw_execute()
+        .map_err(|error| Error::Database(error.to_string()))?;
+
+    Ok(StatementResult::Changes {
+        changes,
+        last_insert_rowid: connection.last_insert_rowid(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn execute_script_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        let script = r#"
+            CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+            -- seed data
+            INSERT INTO users (name) VALUES (:name);
+            INSERT INTO users (name) VALUES ('Bob'), ('Carol');
+            SELECT name FROM users WHERE id = :id;
+            "#
+        .to_string();
+
+        let mut insert_parameters = HashMap::new();
+        insert_parameters.insert(":name".to_string(), JsonValue::String("Alice".to_string()));
+        let mut select_parameters = HashMap::new();
+        select_parameters.insert(":id".to_string(), JsonValue::Number(1.into()));
+        let parameters_list = vec![
+            HashMap::new(),
+            insert_parameters,
+            HashMap::new(),
+            select_parameters,
+        ];
+
+        let results = execute_script(&connection, script, parameters_list, false).unwrap();
+
+        assert_eq!(results.len(), 4);
+        match &results[2] {
+            StatementResult::Changes {
+                changes,
+                last_insert_rowid,
+            } => {
+                assert_eq!(*changes, 2);
+                assert_eq!(*last_insert_rowid, 3);
+            }
+            _ => panic!(),
+        }
+        match &results[3] {
+            StatementResult::Rows { rows } => {
+                assert_eq!(
+                    rows[0].get("name").unwrap(),
+                    &JsonValue::String("Alice".to_string())
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn execute_script_error_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        let script = r#"
+            CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+            INSERT INTO users (name) VALUES (NULL);
+            INSERT INTO users (name) VALUES ('Alice');
+            "#
+        .to_string();
+
+        let result = execute_script(&connection, script.clone(), Vec::new(), false);
+        match result {
+            Err(Error::Script(index, offset, _)) => {
+                assert_eq!(index, 1);
+                assert_eq!(offset, script.find(';').unwrap() + 1);
+            }
+            _ => panic!(),
+        }
+
+        let connection = Connection::open_in_memory().unwrap();
+        let results = execute_script(&connection, script, Vec::new(), true).unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(matches!(results[1], StatementResult::Error { .. }));
+        assert!(matches!(results[2], StatementResult::Changes { .. }));
+    }
+
+    #[test]
+    fn execute_script_offset_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        let script = r#"
+            CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);;
+            /* ; */ ;
+            INSERT INTO users (name) VALUES (:name), (?), ('semi;colon'); -- ;
+            INSERT INTO users (name) VALUES (NULL);
+            "#
+        .to_string();
+
+        let mut parameters = HashMap::new();
+        parameters.insert(":name".to_string(), JsonValue::String("Alice".to_string()));
+        let parameters_list = vec![HashMap::new(), parameters];
+
+        let results = execute_script(&connection, script.clone(), parameters_list, true).unwrap();
+        assert_eq!(results.len(), 3);
+        match &results[2] {
+            StatementResult::Error { offset, .. } => {
+                assert_eq!(*offset, script.find(" -- ;").unwrap());
+            }
+            _ => panic!(),
+        }
+    }
+}
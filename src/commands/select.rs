@@ -1,59 +1,158 @@
 use std::collections::HashMap;
 
-use rusqlite::{types::Value as SqliteValue, Connection, ToSql};
+use rusqlite::{
+    types::{Value as SqliteValue, ValueRef},
+    Connection, Row, Statement,
+};
 
 use serde_json::{Number, Value as JsonValue};
 
-use crate::common::{create_parameters, get_column_names};
+use crate::common::{bind_parameters, get_column_metadata, get_column_names};
 use crate::error::Error;
-use crate::types::{Result, ResultElement, ResultList};
+use crate::types::{ColumnMetadata, Result, ResultElement, ResultList, ResultSet};
 
 pub fn execute_select(
     connection: &Connection,
     sql: String,
     parameters: HashMap<String, JsonValue>,
 ) -> Result<ResultList> {
-    let sql_parameters = create_parameters(&parameters)?;
-    let params = sql_parameters
-        .iter()
-        .map(|(name, value)| (name.as_str(), value.as_ref()))
-        .collect::<Vec<(&str, &dyn ToSql)>>();
+    let mut statement = connection
+        .prepare_cached(&sql)
+        .map_err(|error| Error::Database(error.to_string()))?;
+
+    query_rows(&mut statement, &parameters)
+}
 
+pub fn execute_select_with_metadata(
+    connection: &Connection,
+    sql: String,
+    parameters: HashMap<String, JsonValue>,
+) -> Result<ResultSet> {
     let mut statement = connection
-        .prepare(&sql)
+        .prepare_cached(&sql)
         .map_err(|error| Error::Database(error.to_string()))?;
+    let columns = get_column_metadata(&statement);
+    let rows = query_rows(&mut statement, &parameters)?;
 
-    let column_names = get_column_names(&statement);
+    Ok(ResultSet { columns, rows })
+}
+
+pub fn execute_schema(connection: &Connection, sql: String) -> Result<Vec<ColumnMetadata>> {
+    let statement = connection
+        .prepare_cached(&sql)
+        .map_err(|error| Error::Database(error.to_string()))?;
+    Ok(get_column_metadata(&statement))
+}
+
+pub fn query_rows(
+    statement: &mut Statement<'_>,
+    parameters: &HashMap<String, JsonValue>,
+) -> Result<ResultList> {
+    bind_parameters(statement, parameters)?;
+    let column_names = get_column_names(statement);
 
     let mut result = ResultList::new();
-    let mut rows = statement
-        .query(params.as_slice())
+    let mut rows = statement.raw_query();
+
+    while let Some(row) = rows
+        .next()
+        .map_err(|error| Error::Database(error.to_string()))?
+    {
+        result.push(convert_row(row, &column_names)?);
+    }
+
+    Ok(result)
+}
+
+pub fn execute_select_one(
+    connection: &Connection,
+    sql: String,
+    parameters: HashMap<String, JsonValue>,
+    strict: bool,
+) -> Result<Option<ResultElement>> {
+    let mut statement = connection
+        .prepare_cached(&sql)
         .map_err(|error| Error::Database(error.to_string()))?;
 
-    while let Some(row) = rows.next().unwrap() {
-        let mut map = ResultElement::new();
-        for (index, name) in column_names.iter().enumerate() {
+    bind_parameters(&mut statement, &parameters)?;
+    let column_names = get_column_names(&statement);
+
+    let mut rows = statement.raw_query();
+
+    let result = match rows
+        .next()
+        .map_err(|error| Error::Database(error.to_string()))?
+    {
+        Some(row) => Some(convert_row(row, &column_names)?),
+        None => return Ok(None),
+    };
+
+    if strict
+        && rows
+            .next()
+            .map_err(|error| Error::Database(error.to_string()))?
+            .is_some()
+    {
+        return Err(Error::Database(
+            "The query returned more than one row".to_string(),
+        ));
+    }
+
+    Ok(result)
+}
+
+/// Returns the first column of the first row, failing when the query returns no rows so that
+/// a missing row cannot be mistaken for a NULL value.
+pub fn execute_select_value(
+    connection: &Connection,
+    sql: String,
+    parameters: HashMap<String, JsonValue>,
+) -> Result<JsonValue> {
+    let mut statement = connection
+        .prepare_cached(&sql)
+        .map_err(|error| Error::Database(error.to_string()))?;
+
+    bind_parameters(&mut statement, &parameters)?;
+    let mut rows = statement.raw_query();
+
+    match rows
+        .next()
+        .map_err(|error| Error::Database(error.to_string()))?
+    {
+        Some(row) => {
             let row_value = row
-                .get_ref(index)
+                .get_ref(0)
                 .map_err(|error| Error::Database(error.to_string()))?;
-            let value = match SqliteValue::from(row_value) {
-                SqliteValue::Null => JsonValue::Null,
-                SqliteValue::Integer(value) => JsonValue::Number(value.into()),
-                SqliteValue::Real(value) => JsonValue::Number(Number::from_f64(value).unwrap()),
-                SqliteValue::Text(value) => JsonValue::String(value),
-                SqliteValue::Blob(value) => JsonValue::Array(
-                    value
-                        .iter()
-                        .map(|byte| JsonValue::Number((*byte).into()))
-                        .collect(),
-                ),
-            };
-            map.insert(name.clone(), value);
+            Ok(convert_value(row_value))
         }
-        result.push(map);
+        None => Err(Error::Database("The query returned no rows".to_string())),
     }
+}
 
-    Ok(result)
+pub fn convert_row(row: &Row<'_>, column_names: &[String]) -> Result<ResultElement> {
+    let mut map = ResultElement::new();
+    for (index, name) in column_names.iter().enumerate() {
+        let row_value = row
+            .get_ref(index)
+            .map_err(|error| Error::Database(error.to_string()))?;
+        map.insert(name.clone(), convert_value(row_value));
+    }
+    Ok(map)
+}
+
+pub fn convert_value(row_value: ValueRef<'_>) -> JsonValue {
+    match SqliteValue::from(row_value) {
+        SqliteValue::Null => JsonValue::Null,
+        SqliteValue::Integer(value) => JsonValue::Number(value.into()),
+        SqliteValue::Real(value) => JsonValue::Number(Number::from_f64(value).unwrap()),
+        SqliteValue::Text(value) => JsonValue::String(value),
+        SqliteValue::Blob(value) => JsonValue::Array(
+            value
+                .iter()
+                .map(|byte| JsonValue::Number((*byte).into()))
+                .collect(),
+        ),
+    }
 }
 
 #[cfg(test)]
@@ -122,4 +221,154 @@ mod tests {
 
         assert_eq!(result[0].get("rows").unwrap(), &JsonValue::Number(2.into()));
     }
+
+    #[test]
+    fn execute_query_step_error_test() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        let sql = "SELECT abs(-9223372036854775808)";
+
+        let result = execute_select(&connection, sql.to_string(), HashMap::new());
+        assert!(matches!(result, Err(Error::Database(_))));
+    }
+
+    #[test]
+    fn execute_select_with_metadata_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection.execute_batch(
+            "CREATE TABLE test (id INTEGER PRIMARY KEY, integer_value INTEGER, real_value REAL, text_value TEXT, blob_value BLOB);
+            INSERT INTO test (integer_value, real_value, text_value, blob_value) VALUES (1, 1.1, 'test1', x'010203');"
+        ).unwrap();
+
+        let sql = "SELECT id, text_value AS label, count(*) AS total FROM test";
+
+        let parameters = HashMap::new();
+        let result =
+            execute_select_with_metadata(&connection, sql.to_string(), parameters).unwrap();
+
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(
+            result.rows[0].get("label").unwrap(),
+            &JsonValue::String("test1".to_string())
+        );
+
+        let decl_type = |decl_type| cfg!(feature = "column_decltype").then(|| decl_type);
+        let origin = |origin| cfg!(feature = "column_metadata").then(|| origin);
+
+        let id = &result.columns[0];
+        assert_eq!(id.name, "id");
+        assert_eq!(id.decl_type.as_deref(), decl_type("INTEGER"));
+        assert_eq!(id.database_name.as_deref(), origin("main"));
+        assert_eq!(id.table_name.as_deref(), origin("test"));
+        assert_eq!(id.origin_name.as_deref(), origin("id"));
+
+        let label = &result.columns[1];
+        assert_eq!(label.name, "label");
+        assert_eq!(label.decl_type.as_deref(), decl_type("TEXT"));
+        assert_eq!(label.origin_name.as_deref(), origin("text_value"));
+
+        let total = &result.columns[2];
+        assert_eq!(total.name, "total");
+        assert_eq!(total.decl_type, None);
+        assert_eq!(total.table_name, None);
+        assert_eq!(total.origin_name, None);
+
+        // The cached statement is reused after another statement was prepared.
+        execute_select(&connection, "SELECT 1".to_string(), HashMap::new()).unwrap();
+        let columns = execute_schema(&connection, sql.to_string()).unwrap();
+        assert_eq!(columns[1].table_name.as_deref(), origin("test"));
+        assert_eq!(columns[1].origin_name.as_deref(), origin("text_value"));
+    }
+
+    #[test]
+    fn execute_select_one_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE test (id INTEGER PRIMARY KEY, text_value TEXT);
+            INSERT INTO test (text_value) VALUES ('test1');
+            INSERT INTO test (text_value) VALUES ('test2');",
+            )
+            .unwrap();
+
+        let sql = "SELECT * FROM test WHERE id = :id";
+
+        let mut parameters = HashMap::new();
+        parameters.insert(":id".to_string(), JsonValue::Number(2.into()));
+        let result = execute_select_one(&connection, sql.to_string(), parameters, true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            result.get("text_value").unwrap(),
+            &JsonValue::String("test2".to_string())
+        );
+
+        let mut parameters = HashMap::new();
+        parameters.insert(":id".to_string(), JsonValue::Number(3.into()));
+        let result = execute_select_one(&connection, sql.to_string(), parameters, true).unwrap();
+        assert!(result.is_none());
+
+        let sql = "SELECT * FROM test ORDER BY id";
+
+        let result =
+            execute_select_one(&connection, sql.to_string(), HashMap::new(), false).unwrap();
+        assert_eq!(
+            result.unwrap().get("id").unwrap(),
+            &JsonValue::Number(1.into())
+        );
+
+        let result = execute_select_one(&connection, sql.to_string(), HashMap::new(), true);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn execute_select_value_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE test (id INTEGER PRIMARY KEY, text_value TEXT);
+            INSERT INTO test (text_value) VALUES ('test1');
+            INSERT INTO test (text_value) VALUES (null);",
+            )
+            .unwrap();
+
+        let sql = "SELECT count(*) FROM test";
+        let result = execute_select_value(&connection, sql.to_string(), HashMap::new()).unwrap();
+        assert_eq!(result, JsonValue::Number(2.into()));
+
+        let sql = "SELECT text_value FROM test WHERE id = :id";
+
+        let mut parameters = HashMap::new();
+        parameters.insert(":id".to_string(), JsonValue::Number(2.into()));
+        let result = execute_select_value(&connection, sql.to_string(), parameters).unwrap();
+        assert_eq!(result, JsonValue::Null);
+
+        let mut parameters = HashMap::new();
+        parameters.insert(":id".to_string(), JsonValue::Number(3.into()));
+        let result = execute_select_value(&connection, sql.to_string(), parameters);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn execute_schema_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch("CREATE TABLE test (id INTEGER PRIMARY KEY, text_value TEXT);")
+            .unwrap();
+
+        let columns = execute_schema(
+            &connection,
+            "SELECT text_value, id + 1 AS next FROM test".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(columns.len(), 2);
+        assert_eq!(columns[0].name, "text_value");
+        assert_eq!(
+            columns[0].decl_type.as_deref(),
+            cfg!(feature = "column_decltype").then(|| "TEXT")
+        );
+        assert_eq!(columns[1].name, "next");
+        assert_eq!(columns[1].decl_type, None);
+    }
 }
@@ -0,0 +1,85 @@
+use rusqlite::Connection;
+#[cfg(feature = "serialize")]
+use rusqlite::DatabaseName;
+
+use crate::error::Error;
+use crate::types::Result;
+
+/// Returns the content of the main database of `connection`, as it would be written to a file.
+#[cfg(feature = "serialize")]
+pub fn execute_serialize(connection: &Connection) -> Result<Vec<u8>> {
+    let data = connection
+        .serialize(DatabaseName::Main)
+        .map_err(|error| Error::Serialization(error.to_string()))?;
+    Ok(data.to_vec())
+}
+
+/// Opens an in-memory connection holding a copy of the serialized database `bytes`.
+#[cfg(feature = "serialize")]
+pub fn open_deserialized(bytes: &[u8], read_only: bool) -> Result<Connection> {
+    let mut connection = Connection::open_in_memory()
+        .map_err(|error| Error::OpeningConnection(error.to_string()))?;
+    if bytes.is_empty() {
+        return Ok(connection);
+    }
+
+    connection
+        .deserialize_read_exact(DatabaseName::Main, bytes, bytes.len(), read_only)
+        .map_err(|error| Error::OpeningConnection(error.to_string()))?;
+
+    // The bytes are only read when the database is first used, so invalid data is reported here.
+    connection
+        .query_row("SELECT COUNT(*) FROM sqlite_schema", [], |_| Ok(()))
+        .map_err(|error| Error::OpeningConnection(error.to_string()))?;
+    Ok(connection)
+}
+
+#[cfg(not(feature = "serialize"))]
+pub fn execute_serialize(_connection: &Connection) -> Result<Vec<u8>> {
+    Err(Error::Feature("serialize".to_string()))
+}
+
+#[cfg(not(feature = "serialize"))]
+pub fn open_deserialized(_bytes: &[u8], _read_only: bool) -> Result<Connection> {
+    Err(Error::Feature("serialize".to_string()))
+}
+
+#[cfg(all(test, feature = "serialize"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_deserialize_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE test (id INTEGER PRIMARY KEY, text_value TEXT);
+                INSERT INTO test (text_value) VALUES ('test1'), ('test2');",
+            )
+            .unwrap();
+
+        let bytes = execute_serialize(&connection).unwrap();
+        assert!(bytes.starts_with(b"SQLite format 3\0"));
+
+        let copy = open_deserialized(&bytes, false).unwrap();
+        copy.execute("INSERT INTO test (text_value) VALUES ('test3')", [])
+            .unwrap();
+        let count: i64 = copy
+            .query_row("SELECT COUNT(*) FROM test", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 3);
+
+        let read_only = open_deserialized(&bytes, true).unwrap();
+        assert!(read_only
+            .execute("INSERT INTO test (text_value) VALUES ('test3')", [])
+            .is_err());
+
+        assert!(open_deserialized(b"not a database", false).is_err());
+
+        let empty = Connection::open_in_memory().unwrap();
+        let bytes = execute_serialize(&empty).unwrap();
+        let copy = open_deserialized(&bytes, false).unwrap();
+        copy.execute_batch("CREATE TABLE test (id INTEGER PRIMARY KEY);")
+            .unwrap();
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use serde_json::Value as JsonValue;
+
+use crate::commands::select::query_rows;
+use crate::common::{bind_parameters, OwnedStatement, SharedConnection};
+use crate::error::Error;
+use crate::types::{Result, ResultList};
+
+/// A statement handle kept for the webview. The handle owns its compiled statement, which is
+/// executed again with new parameters until the handle is finalized.
+pub struct PreparedStatement {
+    pub name: String,
+    statement: OwnedStatement,
+}
+
+pub fn create_statement(
+    connection: &SharedConnection,
+    name: String,
+    sql: String,
+) -> Result<PreparedStatement> {
+    let statement = OwnedStatement::new(connection, &sql)?;
+
+    Ok(PreparedStatement { name, statement })
+}
+
+pub fn execute_statement_update(
+    prepared_statement: &mut PreparedStatement,
+    parameters: HashMap<String, JsonValue>,
+) -> Result<()> {
+    let statement = prepared_statement.statement.get_mut();
+    bind_parameters(statement, &parameters)?;
+    statement
+        .raw_execute()
+        .map_err(|error| Error::Database(error.to_string()))?;
+
+    Ok(())
+}
+
+pub fn execute_statement_select(
+    prepared_statement: &mut PreparedStatement,
+    parameters: HashMap<String, JsonValue>,
+) -> Result<ResultList> {
+    query_rows(prepared_statement.statement.get_mut(), &parameters)
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use super::*;
+
+    #[test]
+    fn create_statement_test() {
+        let connection = SharedConnection::new(Connection::open_in_memory().unwrap());
+        connection
+            .execute_batch("CREATE TABLE test (id INTEGER PRIMARY KEY, text_value TEXT);")
+            .unwrap();
+
+        let mut insert = create_statement(
+            &connection,
+            "test".to_string(),
+            "INSERT INTO test (text_value) VALUES (:text_value)".to_string(),
+        )
+        .unwrap();
+        let mut select = create_statement(
+            &connection,
+            "test".to_string(),
+            "SELECT * FROM test WHERE id >= :id ORDER BY id".to_string(),
+        )
+        .unwrap();
+
+        for text in ["test1", "test2"] {
+            let mut parameters = HashMap::new();
+            parameters.insert(
+                ":text_value".to_string(),
+                JsonValue::String(text.to_string()),
+            );
+            execute_statement_update(&mut insert, parameters).unwrap();
+        }
+
+        for (id, count) in [(1, 2), (2, 1)] {
+            let mut parameters = HashMap::new();
+            parameters.insert(":id".to_string(), JsonValue::Number(id.into()));
+            let result = execute_statement_select(&mut select, parameters).unwrap();
+            assert_eq!(result.len(), count);
+        }
+    }
+
+    #[test]
+    fn finalize_statement_test() {
+        let mut connection = SharedConnection::new(Connection::open_in_memory().unwrap());
+
+        let statement =
+            create_statement(&connection, "test".to_string(), "SELECT 1".to_string()).unwrap();
+        assert!(connection.get_mut().is_none());
+
+        drop(statement);
+        let connection = connection.into_inner().unwrap();
+        assert!(connection.close().is_ok());
+    }
+
+    #[test]
+    fn create_statement_invalid_sql_test() {
+        let connection = SharedConnection::new(Connection::open_in_memory().unwrap());
+        let result = create_statement(
+            &connection,
+            "test".to_string(),
+            "INSERT INTO missing VALUES (1)".to_string(),
+        );
+        assert!(result.is_err());
+    }
+}
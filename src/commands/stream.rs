@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use rusqlite::Connection;
+
+use serde_json::Value as JsonValue;
+
+use crate::commands::select::convert_row;
+use crate::common::{bind_parameters, get_column_names};
+use crate::error::Error;
+use crate::types::{Result, ResultList};
+
+/// Runs the select and hands the rows to `emit` in chunks of `chunk_size` as soon as each chunk
+/// is complete. Returns the total number of rows.
+pub fn execute_select_stream<F>(
+    connection: &Connection,
+    sql: String,
+    parameters: HashMap<String, JsonValue>,
+    chunk_size: usize,
+    mut emit: F,
+) -> Result<usize>
+where
+    F: FnMut(ResultList) -> Result<()>,
+{
+    let chunk_size = chunk_size.max(1);
+    let mut statement = connection
+        .prepare_cached(&sql)
+        .map_err(|error| Error::Database(error.to_string()))?;
+
+    bind_parameters(&mut statement, &parameters)?;
+    let column_names = get_column_names(&statement);
+
+    let mut total = 0;
+    let mut chunk = ResultList::with_capacity(chunk_size);
+    let mut rows = statement.raw_query();
+
+    while let Some(row) = rows
+        .next()
+        .map_err(|error| Error::Database(error.to_string()))?
+    {
+        chunk.push(convert_row(row, &column_names)?);
+        total += 1;
+        if chunk.len() == chunk_size {
+            emit(std::mem::replace(
+                &mut chunk,
+                ResultList::with_capacity(chunk_size),
+            ))?;
+        }
+    }
+    if !chunk.is_empty() {
+        emit(chunk)?;
+    }
+
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn execute_select_stream_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE test (id INTEGER PRIMARY KEY, text_value TEXT);
+                INSERT INTO test (text_value) VALUES ('test1'), ('test2'), ('test3'), ('test4'), ('test5');",
+            )
+            .unwrap();
+
+        let sql = "SELECT * FROM test ORDER BY id";
+
+        let mut chunks = Vec::new();
+        let total =
+            execute_select_stream(&connection, sql.to_string(), HashMap::new(), 2, |chunk| {
+                chunks.push(chunk);
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(total, 5);
+        assert_eq!(
+            chunks.iter().map(|chunk| chunk.len()).collect::<Vec<_>>(),
+            vec![2, 2, 1]
+        );
+        assert_eq!(
+            chunks[2][0].get("id").unwrap(),
+            &JsonValue::Number(5.into())
+        );
+    }
+
+    #[test]
+    fn execute_select_stream_emit_error_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE test (id INTEGER PRIMARY KEY);
+                INSERT INTO test (id) VALUES (1), (2), (3);",
+            )
+            .unwrap();
+
+        let mut emitted = 0;
+        let result = execute_select_stream(
+            &connection,
+            "SELECT * FROM test".to_string(),
+            HashMap::new(),
+            1,
+            |_| {
+                emitted += 1;
+                Err(Error::Event("closed".to_string()))
+            },
+        );
+
+        assert!(result.is_err());
+        assert_eq!(emitted, 1);
+    }
+
+    #[test]
+    fn execute_select_stream_query_error_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE test (id INTEGER PRIMARY KEY);
+                INSERT INTO test (id) VALUES (1), (2), (3);",
+            )
+            .unwrap();
+
+        let sql = "SELECT CASE WHEN id = 3 THEN abs(-9223372036854775808) ELSE id END FROM test";
+
+        let mut emitted = Vec::new();
+        let result =
+            execute_select_stream(&connection, sql.to_string(), HashMap::new(), 1, |chunk| {
+                emitted.extend(chunk);
+                Ok(())
+            });
+
+        assert!(result.is_err());
+        assert_eq!(emitted.len(), 2);
+    }
+}
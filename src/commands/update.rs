@@ -1,40 +1,67 @@
 use std::collections::HashMap;
 
-use rusqlite::{Connection, ToSql};
+use rusqlite::Connection;
 
 use serde_json::Value as JsonValue;
 
-use crate::common::create_parameters;
+use crate::commands::savepoint::with_savepoint;
+use crate::common::bind_parameters;
 use crate::error::Error;
-use crate::types::Result;
+use crate::types::{Result, UpdateResult};
 
 pub fn execute_update(
     connection: &Connection,
     sql: String,
     parameters: HashMap<String, JsonValue>,
 ) -> Result<()> {
-    let sql_parameters = create_parameters(&parameters)?;
-    let params = sql_parameters
-        .iter()
-        .map(|(name, value)| (name.as_str(), value.as_ref()))
-        .collect::<Vec<(&str, &dyn ToSql)>>();
-
     let mut statement = connection
-        .prepare(&sql)
+        .prepare_cached(&sql)
         .map_err(|error| Error::Database(error.to_string()))?;
 
+    bind_parameters(&mut statement, &parameters)?;
     statement
-        .execute(params.as_slice())
+        .raw_execute()
         .map_err(|error| Error::Database(error.to_string()))?;
 
     Ok(())
 }
 
+/// Runs the statement once per parameter set inside a single savepoint, so that it also works
+/// inside an open transaction or savepoint. The first failing parameter set rolls back every
+/// change and its index is reported in the error.
+pub fn execute_update_many(
+    connection: &Connection,
+    sql: String,
+    parameters_list: Vec<HashMap<String, JsonValue>>,
+) -> Result<Vec<UpdateResult>> {
+    with_savepoint(connection, || {
+        let mut statement = connection
+            .prepare_cached(&sql)
+            .map_err(|error| Error::Database(error.to_string()))?;
+
+        let mut results = Vec::with_capacity(parameters_list.len());
+        for (index, parameters) in parameters_list.iter().enumerate() {
+            bind_parameters(&mut statement, parameters)
+                .map_err(|error| Error::UpdateMany(index, error.to_string()))?;
+            let changes = statement
+                .raw_execute()
+                .map_err(|error| Error::UpdateMany(index, error.to_string()))?;
+
+            results.push(UpdateResult {
+                changes,
+                last_insert_rowid: connection.last_insert_rowid(),
+            });
+        }
+        Ok(results)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use serde_json::Number;
 
     use super::*;
+    use crate::commands::savepoint::{execute_release, execute_rollback_to, execute_savepoint};
 
     #[test]
     fn execute_update_insert_data_test() {
@@ -123,4 +150,116 @@ mod tests {
             panic!();
         }
     }
+
+    #[test]
+    fn execute_update_many_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch("CREATE TABLE test (id INTEGER PRIMARY KEY, text_value TEXT NOT NULL);")
+            .unwrap();
+
+        let sql = "INSERT INTO test (text_value) VALUES (:text_value)";
+
+        let parameters_list = ["test1", "test2", "test3"]
+            .iter()
+            .map(|text| {
+                let mut parameters = HashMap::new();
+                parameters.insert(
+                    ":text_value".to_string(),
+                    JsonValue::String(text.to_string()),
+                );
+                parameters
+            })
+            .collect::<Vec<_>>();
+        let results = execute_update_many(&connection, sql.to_string(), parameters_list).unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].changes, 1);
+        assert_eq!(results[2].last_insert_rowid, 3);
+
+        let count: i64 = connection
+            .query_row("SELECT count(*) FROM test", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn execute_update_many_rollback_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch("CREATE TABLE test (id INTEGER PRIMARY KEY, text_value TEXT NOT NULL);")
+            .unwrap();
+
+        let sql = "INSERT INTO test (text_value) VALUES (:text_value)";
+
+        let parameters_list = vec![JsonValue::String("test1".to_string()), JsonValue::Null]
+            .into_iter()
+            .map(|value| {
+                let mut parameters = HashMap::new();
+                parameters.insert(":text_value".to_string(), value);
+                parameters
+            })
+            .collect::<Vec<_>>();
+        let result = execute_update_many(&connection, sql.to_string(), parameters_list);
+
+        match result {
+            Err(Error::UpdateMany(index, _)) => assert_eq!(index, 1),
+            _ => panic!(),
+        }
+
+        let count: i64 = connection
+            .query_row("SELECT count(*) FROM test", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn execute_update_many_in_savepoint_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch("CREATE TABLE test (id INTEGER PRIMARY KEY, text_value TEXT NOT NULL);")
+            .unwrap();
+
+        let sql = "INSERT INTO test (text_value) VALUES (:text_value)";
+        let parameters_list = |values: Vec<JsonValue>| {
+            values
+                .into_iter()
+                .map(|value| {
+                    let mut parameters = HashMap::new();
+                    parameters.insert(":text_value".to_string(), value);
+                    parameters
+                })
+                .collect::<Vec<_>>()
+        };
+        let count = || -> i64 {
+            connection
+                .query_row("SELECT count(*) FROM test", [], |row| row.get(0))
+                .unwrap()
+        };
+
+        execute_savepoint(&connection, "outer").unwrap();
+        let results = execute_update_many(
+            &connection,
+            sql.to_string(),
+            parameters_list(vec![JsonValue::String("test1".to_string())]),
+        )
+        .unwrap();
+        assert_eq!(results.len(), 1);
+
+        let result = execute_update_many(
+            &connection,
+            sql.to_string(),
+            parameters_list(vec![
+                JsonValue::String("test2".to_string()),
+                JsonValue::Null,
+            ]),
+        );
+        assert!(result.is_err());
+        assert!(!connection.is_autocommit());
+        assert_eq!(count(), 1);
+
+        execute_rollback_to(&connection, "outer").unwrap();
+        execute_release(&connection, "outer").unwrap();
+        assert_eq!(count(), 0);
+    }
 }
@@ -0,0 +1,108 @@
+use std::fs;
+
+use rusqlite::Connection;
+
+use crate::error::Error;
+use crate::types::{Result, VacuumResult};
+
+/// Rebuilds the main database of `connection` to reclaim the space of deleted rows.
+pub fn execute_vacuum(connection: &Connection) -> Result<VacuumResult> {
+    let size_before = database_size(connection)?;
+    connection
+        .execute_batch("VACUUM")
+        .map_err(|error| Error::Database(error.to_string()))?;
+
+    Ok(VacuumResult {
+        size_before,
+        size_after: database_size(connection)?,
+    })
+}
+
+/// Writes a compacted copy of the main database of `connection` into the new file `path`.
+pub fn execute_vacuum_into(connection: &Connection, path: &str) -> Result<VacuumResult> {
+    let size_before = database_size(connection)?;
+    connection
+        .execute("VACUUM INTO ?1", [path])
+        .map_err(|error| Error::Database(error.to_string()))?;
+    let size_after = fs::metadata(path)
+        .map_err(|error| Error::Database(error.to_string()))?
+        .len();
+
+    Ok(VacuumResult {
+        size_before,
+        size_after,
+    })
+}
+
+/// Lets SQLite refresh its statistics and, if the database uses `auto_vacuum = INCREMENTAL`,
+/// release its free pages. Meant to be run before closing the connection.
+pub fn execute_optimize(connection: &Connection) -> Result<()> {
+    connection
+        .execute_batch("PRAGMA optimize; PRAGMA incremental_vacuum;")
+        .map_err(|error| Error::Database(error.to_string()))
+}
+
+/// Returns the size in bytes of the content of the main database, its page count times its page
+/// size. The file may differ, for instance while changes are still in the write-ahead log, and
+/// an in-memory database has no file at all.
+fn database_size(connection: &Connection) -> Result<u64> {
+    let size: i64 = connection
+        .query_row(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|error| Error::Database(error.to_string()))?;
+    Ok(size as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_database() -> Connection {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE test (id INTEGER PRIMARY KEY, text_value TEXT);
+                WITH RECURSIVE numbers(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM numbers WHERE n < 200)
+                INSERT INTO test (text_value) SELECT printf('%.500c', 'x') FROM numbers;
+                DELETE FROM test WHERE id > 10;",
+            )
+            .unwrap();
+        connection
+    }
+
+    #[test]
+    fn execute_vacuum_test() {
+        let connection = create_database();
+
+        let result = execute_vacuum(&connection).unwrap();
+
+        assert!(result.size_after < result.size_before);
+        let count: i64 = connection
+            .query_row("SELECT COUNT(*) FROM test", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 10);
+        execute_optimize(&connection).unwrap();
+    }
+
+    #[test]
+    fn execute_vacuum_into_test() {
+        let connection = create_database();
+        let path = std::env::temp_dir().join(format!("vacuum_into_{}.db", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let result = execute_vacuum_into(&connection, path).unwrap();
+        assert!(result.size_after < result.size_before);
+        assert!(execute_vacuum_into(&connection, path).is_err());
+
+        let copy = Connection::open(path).unwrap();
+        let count: i64 = copy
+            .query_row("SELECT COUNT(*) FROM test", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 10);
+        drop(copy);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::{HashMap, HashSet};
+
+use rusqlite::Connection;
+
+use serde_json::Value as JsonValue;
+
+use crate::commands::select::execute_select;
+use crate::common::quote_identifier;
+use crate::error::Error;
+use crate::types::{Result, ResultList};
+
+pub struct Watch {
+    pub name: String,
+    pub sql: String,
+    pub parameters: HashMap<String, JsonValue>,
+    pub tables: HashSet<String>,
+}
+
+/// Runs the select and returns it with the rows, along with the tables it reads so that it can
+/// be run again when one of them changes.
+pub fn create_watch(
+    connection: &Connection,
+    name: String,
+    sql: String,
+    parameters: HashMap<String, JsonValue>,
+) -> Result<(Watch, ResultList)> {
+    let tables = read_tables(connection, &sql)?;
+    let rows = execute_select(connection, sql.clone(), parameters.clone())?;
+
+    Ok((
+        Watch {
+            name,
+            sql,
+            parameters,
+            tables,
+        },
+        rows,
+    ))
+}
+
+/// Returns the tables read by the statement, found in the program SQLite compiles it to: every
+/// table or index the program opens for reading is looked up by its root page in the schema of
+/// its database. Views are replaced by the tables they read.
+pub fn read_tables(connection: &Connection, sql: &str) -> Result<HashSet<String>> {
+    let mut statement = connection
+        .prepare(&format!("EXPLAIN {}", sql))
+        .map_err(|error| Error::Database(error.to_string()))?;
+    // The parameters of the statement are left unbound, which `raw_query` allows.
+    let mut rows = statement.raw_query();
+    let mut pages = HashSet::new();
+    while let Some(row) = rows
+        .next()
+        .map_err(|error| Error::Database(error.to_string()))?
+    {
+        let opcode: String = row
+            .get(1)
+            .map_err(|error| Error::Database(error.to_string()))?;
+        if opcode == "OpenRead" || opcode == "ReopenIdx" {
+            let page: i64 = row
+                .get(3)
+                .map_err(|error| Error::Database(error.to_string()))?;
+            let database: i64 = row
+                .get(4)
+                .map_err(|error| Error::Database(error.to_string()))?;
+            pages.insert((database, page));
+        }
+    }
+
+    let mut tables = HashSet::new();
+    for (database, name) in list_databases(connection)? {
+        let schema_sql = format!(
+            "SELECT tbl_name, rootpage FROM {}.sqlite_schema WHERE rootpage > 0",
+            quote_identifier(&name)
+        );
+        let mut schema = connection
+            .prepare(&schema_sql)
+            .map_err(|error| Error::Database(error.to_string()))?;
+        let rows = schema
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|error| Error::Database(error.to_string()))?;
+        tables.extend(
+            rows.into_iter()
+                .filter(|(_, page)| pages.contains(&(database, *page)))
+                .map(|(table, _)| table),
+        );
+    }
+    Ok(tables)
+}
+
+/// Returns the index and name of every database of the connection, the index being the one
+/// the compiled programs refer to.
+fn list_databases(connection: &Connection) -> Result<Vec<(i64, String)>> {
+    let mut statement = connection
+        .prepare("PRAGMA database_list")
+        .map_err(|error| Error::Database(error.to_string()))?;
+    let databases = statement
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|error| Error::Database(error.to_string()))?;
+    Ok(databases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_tables_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+                CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER NOT NULL, total REAL);
+                CREATE TABLE logs (id INTEGER PRIMARY KEY, message TEXT NOT NULL);
+                CREATE VIEW user_totals AS SELECT users.name, sum(orders.total) AS total FROM users JOIN orders ON orders.user_id = users.id GROUP BY users.id;",
+            )
+            .unwrap();
+
+        let tables = read_tables(&connection, "SELECT * FROM user_totals").unwrap();
+        assert_eq!(
+            tables,
+            HashSet::from(["users".to_string(), "orders".to_string()])
+        );
+
+        let tables = read_tables(&connection, "SELECT count(*) FROM logs").unwrap();
+        assert_eq!(tables, HashSet::from(["logs".to_string()]));
+
+        connection
+            .execute_batch(
+                "CREATE INDEX users_name ON users (name);
+                CREATE TEMP TABLE drafts (id INTEGER PRIMARY KEY, user_id INTEGER NOT NULL);",
+            )
+            .unwrap();
+        let tables = read_tables(
+            &connection,
+            "SELECT count(*) FROM drafts WHERE user_id IN (SELECT id FROM users WHERE name = :name)",
+        )
+        .unwrap();
+        assert_eq!(
+            tables,
+            HashSet::from(["drafts".to_string(), "users".to_string()])
+        );
+
+        assert!(read_tables(&connection, "SELECT * FROM missing").is_err());
+    }
+
+    #[test]
+    fn create_watch_test() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+                INSERT INTO users (name) VALUES ('Alice');",
+            )
+            .unwrap();
+
+        let (watch, rows) = create_watch(
+            &connection,
+            "test".to_string(),
+            "SELECT name FROM users".to_string(),
+            HashMap::new(),
+        )
+        .unwrap();
+
+        assert_eq!(watch.tables, HashSet::from(["users".to_string()]));
+        assert_eq!(
+            rows[0].get("name").unwrap(),
+            &JsonValue::String("Alice".to_string())
+        );
+    }
+}
@@ -1,10 +1,101 @@
 use crate::{
     error::Error,
-    types::{Result, SQLParameter},
+    types::{ColumnMetadata, Result, SQLParameter},
 };
-use rusqlite::{types::Value as SqliteValue, Statement};
+use rusqlite::{types::Value as SqliteValue, Connection, Statement};
 use serde_json::Value as JsonValue;
-use std::collections::HashMap;
+use std::{collections::HashMap, mem, ops::Deref, ptr::NonNull, sync::Arc};
+
+/// A connection managed by the plugin. Cursors, prepared statements and backups hold a reference
+/// to the connection they use, which keeps it alive and in place until they are dropped.
+#[derive(Clone)]
+pub struct SharedConnection(Arc<Connection>);
+
+// SAFETY: a connection may move between threads but not be used from two threads at once. The
+// plugin only uses a connection, and the cursors and statements referring to it, while holding
+// the lock of the connections map.
+unsafe impl Send for SharedConnection {}
+
+impl SharedConnection {
+    // Sent between threads under the lock, as described above.
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn new(connection: Connection) -> Self {
+        SharedConnection(Arc::new(connection))
+    }
+
+    /// Returns the connection for exclusive use, unless cursors or statements still refer to it.
+    pub fn get_mut(&mut self) -> Option<&mut Connection> {
+        Arc::get_mut(&mut self.0)
+    }
+
+    /// Returns the number of references to the connection, this one included.
+    pub fn references(&self) -> usize {
+        Arc::strong_count(&self.0)
+    }
+
+    /// Returns the connection to close it, unless cursors or statements still refer to it.
+    pub fn into_inner(self) -> Option<Connection> {
+        Arc::try_unwrap(self.0).ok()
+    }
+}
+
+impl Deref for SharedConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        &self.0
+    }
+}
+
+/// A statement compiled for longer than a single command. rusqlite ties a statement to a borrow
+/// of its connection, which the plugin state cannot hold, so the borrow is extended to `'static`
+/// and the connection is kept alive by the reference the statement holds.
+pub struct OwnedStatement {
+    statement: NonNull<Statement<'static>>,
+    // Dropped after the statement, which finalizes itself on the connection.
+    _connection: SharedConnection,
+}
+
+// SAFETY: see `SharedConnection`, whose lock also guards the statements compiled on it.
+unsafe impl Send for OwnedStatement {}
+
+impl OwnedStatement {
+    pub fn new(connection: &SharedConnection, sql: &str) -> Result<Self> {
+        let statement = connection
+            .prepare(sql)
+            .map_err(|error| Error::Database(error.to_string()))?;
+        // SAFETY: the connection lives in an `Arc` that `_connection` keeps alive until the
+        // statement is dropped, so the borrow never dangles.
+        let statement = unsafe { mem::transmute::<Statement<'_>, Statement<'static>>(statement) };
+
+        Ok(OwnedStatement {
+            statement: NonNull::from(Box::leak(Box::new(statement))),
+            _connection: connection.clone(),
+        })
+    }
+
+    pub fn get_mut(&mut self) -> &mut Statement<'static> {
+        // SAFETY: the borrow is tied to `self`, which owns the statement.
+        unsafe { self.statement.as_mut() }
+    }
+
+    /// Returns the statement for a borrow that may outlive `self`, such as the rows of a cursor.
+    ///
+    /// # Safety
+    ///
+    /// The borrow must end before `self` is dropped, and no other borrow of the statement may be
+    /// used meanwhile.
+    pub unsafe fn statement<'a>(&mut self) -> &'a mut Statement<'static> {
+        &mut *self.statement.as_ptr()
+    }
+}
+
+impl Drop for OwnedStatement {
+    fn drop(&mut self) {
+        // SAFETY: the statement comes from `Box::leak` in `new` and is released only here.
+        drop(unsafe { Box::from_raw(self.statement.as_ptr()) });
+    }
+}
 
 pub fn calculate_hash(text: &String) -> String {
     let digest = md5::compute(text.as_bytes());
@@ -16,6 +107,11 @@ pub fn create_parameters(parameters: &HashMap<String, JsonValue>) -> Result<Vec<
     for (name, value) in parameters {
         if value.is_null() {
             params.push((name.to_string(), Box::new(SqliteValue::Null)));
+        } else if value.is_boolean() {
+            params.push((
+                name.clone(),
+                Box::new(SqliteValue::Integer(value.as_bool().unwrap() as i64)),
+            ));
         } else if value.is_i64() {
             params.push((
                 name.clone(),
@@ -50,6 +146,41 @@ pub fn create_parameters(parameters: &HashMap<String, JsonValue>) -> Result<Vec<
     Ok(params)
 }
 
+/// Binds the named parameters to the statement, which then runs with `raw_execute` or
+/// `raw_query`. Parameters left out are bound to NULL, and names missing from the statement fail
+/// like they do in `Statement::query`.
+pub fn bind_parameters(
+    statement: &mut Statement<'_>,
+    parameters: &HashMap<String, JsonValue>,
+) -> Result<()> {
+    statement.clear_bindings();
+    for (name, value) in create_parameters(parameters)? {
+        let index = statement
+            .parameter_index(&name)
+            .map_err(|error| Error::Database(error.to_string()))?
+            .ok_or_else(|| {
+                Error::Database(rusqlite::Error::InvalidParameterName(name.clone()).to_string())
+            })?;
+        statement
+            .raw_bind_parameter(index, value)
+            .map_err(|error| Error::Database(error.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Fails with `Error::Feature` when the cargo `feature` a command relies on is disabled.
+pub fn require_feature(enabled: bool, feature: &str) -> Result<()> {
+    if enabled {
+        Ok(())
+    } else {
+        Err(Error::Feature(feature.to_string()))
+    }
+}
+
+pub fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
 pub fn get_column_names(statement: &Statement<'_>) -> Vec<String> {
     let mut column_names = Vec::<String>::new();
     for name in statement.column_names() {
@@ -57,3 +188,84 @@ pub fn get_column_names(statement: &Statement<'_>) -> Vec<String> {
     }
     column_names
 }
+
+/// Reads the name, declared type and origin of each column of a prepared statement.
+pub fn get_column_metadata(statement: &Statement<'_>) -> Vec<ColumnMetadata> {
+    let decl_types = get_decl_types(statement);
+    let origins = get_column_origins(statement);
+    statement
+        .column_names()
+        .into_iter()
+        .zip(decl_types)
+        .zip(origins)
+        .map(
+            |((name, decl_type), (database_name, table_name, origin_name))| ColumnMetadata {
+                name: name.to_string(),
+                decl_type,
+                database_name,
+                table_name,
+                origin_name,
+            },
+        )
+        .collect()
+}
+
+#[cfg(feature = "column_decltype")]
+fn get_decl_types(statement: &Statement<'_>) -> Vec<Option<String>> {
+    statement
+        .columns()
+        .iter()
+        .map(|column| column.decl_type().map(str::to_string))
+        .collect()
+}
+
+#[cfg(not(feature = "column_decltype"))]
+fn get_decl_types(statement: &Statement<'_>) -> Vec<Option<String>> {
+    vec![None; statement.column_count()]
+}
+
+type ColumnOrigin = (Option<String>, Option<String>, Option<String>);
+
+/// Columns computed from expressions have no origin. The origins are only known when SQLite is
+/// compiled with `SQLITE_ENABLE_COLUMN_METADATA`, which the `column_metadata` feature requests.
+#[cfg(feature = "column_metadata")]
+fn get_column_origins(statement: &Statement<'_>) -> Vec<ColumnOrigin> {
+    statement
+        .columns_with_metadata()
+        .iter()
+        .map(|column| {
+            (
+                column.database_name().map(str::to_string),
+                column.table_name().map(str::to_string),
+                column.origin_name().map(str::to_string),
+            )
+        })
+        .collect()
+}
+
+#[cfg(not(feature = "column_metadata"))]
+fn get_column_origins(statement: &Statement<'_>) -> Vec<ColumnOrigin> {
+    vec![(None, None, None); statement.column_count()]
+}
+
+/// Returns the length in bytes of the SQL text `statement` was compiled from, which rusqlite does
+/// not expose. The text runs from the end of the previous statement of a batch to the `;` of this
+/// one, so the lengths add up to the offset of each statement.
+///
+/// `expanded_sql` reproduces that text with each parameter replaced by its value, so every
+/// parameter is first bound to a value written with as many characters as its name. The
+/// bindings are cleared afterwards.
+pub fn statement_length(statement: &mut Statement<'_>) -> Result<usize> {
+    for index in 1..=statement.parameter_count() {
+        let length = statement.parameter_name(index).map_or(1, str::len);
+        if length < 2 {
+            statement.raw_bind_parameter(index, 1)
+        } else {
+            statement.raw_bind_parameter(index, "a".repeat(length - 2))
+        }
+        .map_err(|error| Error::Database(error.to_string()))?;
+    }
+    let length = statement.expanded_sql().map(|sql| sql.len());
+    statement.clear_bindings();
+    length.ok_or_else(|| Error::Database("Cannot read the SQL of the statement".to_string()))
+}
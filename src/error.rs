@@ -6,14 +6,36 @@ pub enum Error {
         "Please run the open_in_memory or open_in_path method first to establish a connection to the database."
     )]
     Connection(),
+    #[error("The cursor {0} does not exist or has already been closed.")]
+    Cursor(u64),
+    #[error("The statement {0} does not exist or has already been finalized.")]
+    Statement(u64),
+    #[error("The watch {0} does not exist or has already been removed.")]
+    Watch(u32),
+    #[error("The watch {0} already exists.")]
+    WatchExists(u32),
+    #[error("The path {0} is outside of the filesystem scope of the application.")]
+    Path(String),
     #[error("Database error: {0}")]
     Database(String),
+    #[error("Error executing parameter set {0}: {1}")]
+    UpdateMany(usize, String),
+    #[error("Error executing statement {0} at offset {1}: {2}")]
+    Script(usize, usize, String),
     #[error("Migration error: {0}")]
     Migration(String),
     #[error("Opening connection error: {0}")]
     OpeningConnection(String),
     #[error("Closing connection error: {0}")]
     ClosingConnection(String),
+    #[error("The {0} feature of tauri-plugin-rusqlite is not enabled.")]
+    Feature(String),
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+    #[error("Event error: {0}")]
+    Event(String),
 }
 
 impl Serialize for Error {
@@ -0,0 +1,251 @@
+use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::sync::Arc;
+
+#[cfg(feature = "window")]
+use rusqlite::functions::WindowAggregate;
+use rusqlite::functions::{Aggregate, Context, FunctionFlags, SqlFnOutput};
+use rusqlite::Connection;
+
+use crate::types::ConnectionInitializer;
+
+/// Returns an initializer creating the scalar function `name` on a connection. The same function
+/// is shared by every connection, so it must not keep per-connection state.
+pub fn scalar_function<F, T>(
+    name: &str,
+    n_arg: i32,
+    flags: FunctionFlags,
+    function: F,
+) -> ConnectionInitializer
+where
+    F: Fn(&Context<'_>) -> rusqlite::Result<T> + Send + Sync + RefUnwindSafe + 'static,
+    T: SqlFnOutput,
+{
+    let name = name.to_string();
+    let function = Arc::new(function);
+    Arc::new(move |connection: &Connection| {
+        let function = function.clone();
+        connection.create_scalar_function(&name, n_arg, flags, move |context| function(context))
+    })
+}
+
+/// Returns an initializer creating the aggregate function `name` on a connection. The aggregate
+/// is shared by every connection, so its state must live in the accumulator.
+pub fn aggregate_function<A, D, T>(
+    name: &str,
+    n_arg: i32,
+    flags: FunctionFlags,
+    aggregate: D,
+) -> ConnectionInitializer
+where
+    A: RefUnwindSafe + UnwindSafe + 'static,
+    D: Aggregate<A, T> + Send + Sync + 'static,
+    T: SqlFnOutput + 'static,
+{
+    let name = name.to_string();
+    let aggregate = Arc::new(aggregate);
+    Arc::new(move |connection: &Connection| {
+        connection.create_aggregate_function(
+            &name,
+            n_arg,
+            flags,
+            SharedAggregate(aggregate.clone()),
+        )
+    })
+}
+
+/// Returns an initializer creating the aggregate window function `name` on a connection.
+#[cfg(feature = "window")]
+pub fn window_function<A, W, T>(
+    name: &str,
+    n_arg: i32,
+    flags: FunctionFlags,
+    aggregate: W,
+) -> ConnectionInitializer
+where
+    A: RefUnwindSafe + UnwindSafe + 'static,
+    W: WindowAggregate<A, T> + Send + Sync + 'static,
+    T: SqlFnOutput + 'static,
+{
+    let name = name.to_string();
+    let aggregate = Arc::new(aggregate);
+    Arc::new(move |connection: &Connection| {
+        connection.create_window_function(&name, n_arg, flags, SharedAggregate(aggregate.clone()))
+    })
+}
+
+/// rusqlite takes ownership of the aggregate given to each connection, so the one registered
+/// on the builder is shared behind an `Arc`.
+struct SharedAggregate<D>(Arc<D>);
+
+impl<A, D, T> Aggregate<A, T> for SharedAggregate<D>
+where
+    A: RefUnwindSafe + UnwindSafe,
+    D: Aggregate<A, T>,
+    T: SqlFnOutput,
+{
+    fn init(&self, context: &mut Context<'_>) -> rusqlite::Result<A> {
+        self.0.init(context)
+    }
+
+    fn step(&self, context: &mut Context<'_>, accumulator: &mut A) -> rusqlite::Result<()> {
+        self.0.step(context, accumulator)
+    }
+
+    fn finalize(&self, context: &mut Context<'_>, accumulator: Option<A>) -> rusqlite::Result<T> {
+        self.0.finalize(context, accumulator)
+    }
+}
+
+#[cfg(feature = "window")]
+impl<A, W, T> WindowAggregate<A, T> for SharedAggregate<W>
+where
+    A: RefUnwindSafe + UnwindSafe,
+    W: WindowAggregate<A, T>,
+    T: SqlFnOutput,
+{
+    fn value(&self, accumulator: Option<&mut A>) -> rusqlite::Result<T> {
+        self.0.value(accumulator)
+    }
+
+    fn inverse(&self, context: &mut Context<'_>, accumulator: &mut A) -> rusqlite::Result<()> {
+        self.0.inverse(context, accumulator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scalar_function_test() {
+        let initializer = scalar_function(
+            "normalize",
+            1,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |context| Ok(context.get::<String>(0)?.trim().to_lowercase()),
+        );
+
+        for _ in 0..2 {
+            let connection = Connection::open_in_memory().unwrap();
+            initializer(&connection).unwrap();
+
+            let value: String = connection
+                .query_row("SELECT normalize('  Ärger ')", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(value, "ärger");
+        }
+    }
+
+    struct Median;
+
+    impl Aggregate<Vec<f64>, Option<f64>> for Median {
+        fn init(&self, _: &mut Context<'_>) -> rusqlite::Result<Vec<f64>> {
+            Ok(Vec::new())
+        }
+
+        fn step(&self, context: &mut Context<'_>, values: &mut Vec<f64>) -> rusqlite::Result<()> {
+            if let Some(value) = context.get::<Option<f64>>(0)? {
+                values.push(value);
+            }
+            Ok(())
+        }
+
+        fn finalize(
+            &self,
+            _: &mut Context<'_>,
+            values: Option<Vec<f64>>,
+        ) -> rusqlite::Result<Option<f64>> {
+            Ok(values.and_then(|values| median(&values)))
+        }
+    }
+
+    #[cfg(feature = "window")]
+    impl WindowAggregate<Vec<f64>, Option<f64>> for Median {
+        fn value(&self, values: Option<&mut Vec<f64>>) -> rusqlite::Result<Option<f64>> {
+            Ok(values.and_then(|values| median(values)))
+        }
+
+        fn inverse(
+            &self,
+            context: &mut Context<'_>,
+            values: &mut Vec<f64>,
+        ) -> rusqlite::Result<()> {
+            if let Some(value) = context.get::<Option<f64>>(0)? {
+                if let Some(index) = values.iter().position(|element| *element == value) {
+                    values.remove(index);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn median(values: &[f64]) -> Option<f64> {
+        let mut values = values.to_vec();
+        values.sort_by(f64::total_cmp);
+        let middle = values.len() / 2;
+        match values.len() {
+            0 => None,
+            length if length % 2 == 0 => Some((values[middle - 1] + values[middle]) / 2.0),
+            _ => Some(values[middle]),
+        }
+    }
+
+    fn create_numbers(connection: &Connection) {
+        connection
+            .execute_batch(
+                "CREATE TABLE numbers (id INTEGER PRIMARY KEY, value REAL);
+                INSERT INTO numbers (value) VALUES (4), (1), (NULL), (3), (10);",
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn aggregate_function_test() {
+        let initializer = aggregate_function("median", 1, FunctionFlags::SQLITE_UTF8, Median);
+
+        for _ in 0..2 {
+            let connection = Connection::open_in_memory().unwrap();
+            initializer(&connection).unwrap();
+            create_numbers(&connection);
+
+            let value: Option<f64> = connection
+                .query_row("SELECT median(value) FROM numbers", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(value, Some(3.5));
+
+            let value: Option<f64> = connection
+                .query_row(
+                    "SELECT median(value) FROM numbers WHERE id > 5",
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(value, None);
+        }
+    }
+
+    #[cfg(feature = "window")]
+    #[test]
+    fn window_function_test() {
+        let initializer = window_function("median", 1, FunctionFlags::SQLITE_UTF8, Median);
+        let connection = Connection::open_in_memory().unwrap();
+        initializer(&connection).unwrap();
+        create_numbers(&connection);
+
+        let mut statement = connection
+            .prepare(
+                "SELECT median(value) OVER (ORDER BY id ROWS BETWEEN 1 PRECEDING AND CURRENT ROW)
+                FROM numbers ORDER BY id",
+            )
+            .unwrap();
+        let values = statement
+            .query_map([], |row| row.get::<_, Option<f64>>(0))
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(
+            values,
+            vec![Some(4.0), Some(2.5), Some(1.0), Some(3.0), Some(6.5)]
+        );
+    }
+}
@@ -1,65 +1,357 @@
-use crate::types::{Result, ResultList};
+pub use crate::types::Result;
+use crate::types::{
+    AttachOptions, BackupProgress, ColumnMetadata, ConnectionInitializer, ForeignKey, KeyProvider,
+    ResultElement, ResultList, SchemaObject, SelectResult, StatementResult, StreamEnd, TableColumn,
+    TableIndex, TransactionChanges, UpdateResult, VacuumResult, WatchUpdate,
+};
+#[cfg(feature = "collation")]
+use collations::collation;
+#[cfg(feature = "unicode_collations")]
+use collations::{natural, unicode_ci};
 use commands::{
-    batch::execute_batch, migration::execute_migration, select::execute_select,
-    update::execute_update,
+    attach::{Attachment, Attachments},
+    backup::{execute_backup, execute_restore},
+    batch::{execute_batch, execute_batch_in_transaction},
+    cursor::{create_cursor, fetch_cursor, Cursor},
+    encryption::{apply_key, execute_rekey},
+    hooks::{register_hooks, HookEvent, Hooks, Pending, PendingChanges},
+    introspection::{
+        list_columns, list_foreign_keys, list_indexes, list_tables, list_triggers, list_views,
+    },
+    migration::execute_migration,
+    savepoint::{execute_release, execute_rollback_to, execute_savepoint},
+    script::execute_script,
+    select::{
+        execute_schema, execute_select, execute_select_one, execute_select_value,
+        execute_select_with_metadata,
+    },
+    serialize::{execute_serialize, open_deserialized},
+    statement::{
+        create_statement, execute_statement_select, execute_statement_update, PreparedStatement,
+    },
+    stream::execute_select_stream,
+    update::{execute_update, execute_update_many},
+    vacuum::{execute_optimize, execute_vacuum, execute_vacuum_into},
+    watch::{create_watch, Watch},
 };
-use error::Error;
+use common::{require_feature, SharedConnection};
+pub use error::Error;
+#[cfg(feature = "window")]
+use functions::window_function;
+#[cfg(feature = "functions")]
+use functions::{aggregate_function, scalar_function};
+#[cfg(feature = "window")]
+use rusqlite::functions::WindowAggregate;
+#[cfg(feature = "functions")]
+use rusqlite::functions::{Aggregate, Context, FunctionFlags, SqlFnOutput};
 use rusqlite::{Connection, OpenFlags};
 use serde_json::Value as JsonValue;
-use std::{collections::HashMap, sync::Mutex};
-use tauri::{
-    command,
-    plugin::{Builder, TauriPlugin},
-    Manager, Runtime, State,
+use std::{
+    collections::HashMap,
+    mem,
+    ops::{Deref, DerefMut},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc, Mutex, MutexGuard,
+    },
 };
+use tauri::{command, plugin::TauriPlugin, AppHandle, Manager, Runtime, State};
 use types::Migrations;
 
+mod codegen;
+#[cfg(feature = "collation")]
+mod collations;
 mod commands;
 mod common;
 mod error;
+#[cfg(feature = "functions")]
+mod functions;
+mod query;
 mod types;
 
+pub use codegen::generate_typescript;
+pub use query::Query;
+pub use rusqlite;
+
+/// Connections opened by the plugin, along with the pending changes of those whose commit hook
+/// ran while they were locked.
+struct ConfigState {
+    connections: Mutex<HashMap<String, SharedConnection>>,
+    committing: Mutex<HashMap<String, Pending>>,
+    /// Sends the committed changes to the thread reporting them.
+    commits: Mutex<mpsc::Sender<TransactionChanges>>,
+}
+
+impl ConfigState {
+    fn new(commits: mpsc::Sender<TransactionChanges>) -> Self {
+        ConfigState {
+            connections: Mutex::default(),
+            committing: Mutex::default(),
+            commits: Mutex::new(commits),
+        }
+    }
+
+    /// Locks the connections, which are only used while locked.
+    fn lock(&self) -> Connections<'_> {
+        Connections {
+            connections: self.connections.lock().unwrap(),
+            state: self,
+        }
+    }
+}
+
+/// The locked connections. Releasing them settles the commits made meanwhile, before another
+/// command can run on the connections, and sends the committed changes to be reported.
+struct Connections<'a> {
+    connections: MutexGuard<'a, HashMap<String, SharedConnection>>,
+    state: &'a ConfigState,
+}
+
+impl Deref for Connections<'_> {
+    type Target = HashMap<String, SharedConnection>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.connections
+    }
+}
+
+impl DerefMut for Connections<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.connections
+    }
+}
+
+impl Drop for Connections<'_> {
+    fn drop(&mut self) {
+        let committing = mem::take(&mut *self.state.committing.lock().unwrap());
+        for (name, pending) in committing {
+            let connection = match self.connections.get(&name) {
+                Some(connection) => connection,
+                None => continue,
+            };
+            if let Some(commit) = pending.lock().unwrap().settle(connection, &name) {
+                let _ = self.state.commits.lock().unwrap().send(commit);
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct CursorState(Mutex<HashMap<u64, Cursor>>, AtomicU64);
+
 #[derive(Default)]
-struct ConfigState(Mutex<HashMap<String, Connection>>);
+struct StatementState(Mutex<HashMap<u64, PreparedStatement>>, AtomicU64);
+
+#[derive(Default)]
+struct HookState(Mutex<HashMap<String, Hooks>>);
+
+#[derive(Default)]
+struct WatchState(Mutex<HashMap<u32, Watch>>);
+
+/// Databases attached to each connection, attached again when the connection is reopened.
+#[derive(Default)]
+struct AttachmentState(Mutex<Attachments>);
+
+struct InitializerState(Vec<ConnectionInitializer>);
+
+struct KeyState(Option<KeyProvider>);
 
 #[command]
-async fn open_in_memory(state: State<'_, ConfigState>, name: String) -> Result<()> {
+async fn open_in_memory<R: Runtime>(
+    app: AppHandle<R>,
+    state: State<'_, ConfigState>,
+    name: String,
+    cache_capacity: Option<usize>,
+) -> Result<()> {
     let connection = Connection::open_in_memory()
         .map_err(|error| Error::OpeningConnection(error.to_string()))?;
 
-    insert_connection(state, connection, name)
+    insert_connection(app, state, connection, name, cache_capacity)
 }
 
 #[command]
-async fn open_in_path(state: State<'_, ConfigState>, path: String) -> Result<()> {
+async fn open_from_bytes<R: Runtime>(
+    app: AppHandle<R>,
+    state: State<'_, ConfigState>,
+    name: String,
+    bytes: Vec<u8>,
+    read_only: Option<bool>,
+    cache_capacity: Option<usize>,
+) -> Result<()> {
+    let connection = open_deserialized(&bytes, read_only.unwrap_or(false))?;
+
+    insert_connection(app, state, connection, name, cache_capacity)
+}
+
+#[command]
+async fn open_in_path<R: Runtime>(
+    app: AppHandle<R>,
+    state: State<'_, ConfigState>,
+    path: String,
+    cache_capacity: Option<usize>,
+    key: Option<String>,
+) -> Result<()> {
     let connection = Connection::open_with_flags(path.clone(), OpenFlags::default())
         .map_err(|error| Error::OpeningConnection(error.to_string()))?;
 
-    insert_connection(state, connection, path)
+    let key = match (key, &app.state::<KeyState>().0) {
+        (Some(key), _) => Some(key),
+        (None, Some(key_provider)) => key_provider(&path),
+        (None, None) => None,
+    };
+    if let Some(key) = key {
+        apply_key(&connection, &key)?;
+    }
+
+    insert_connection(app, state, connection, path, cache_capacity)
 }
 
-fn insert_connection(
+fn insert_connection<R: Runtime>(
+    app: AppHandle<R>,
     state: State<'_, ConfigState>,
     connection: Connection,
     name: String,
+    cache_capacity: Option<usize>,
 ) -> Result<()> {
-    let mut connections = state.0.lock().unwrap();
+    if let Some(cache_capacity) = cache_capacity {
+        connection.set_prepared_statement_cache_capacity(cache_capacity);
+    }
+
+    let mut connections = state.lock();
     let contains_key = connections.contains_key(&name);
 
     if !contains_key {
-        connections.insert(name.clone(), connection);
+        for initializer in app.state::<InitializerState>().0.iter() {
+            initializer(&connection)
+                .map_err(|error| Error::OpeningConnection(error.to_string()))?;
+        }
+        app.state::<AttachmentState>()
+            .0
+            .lock()
+            .unwrap()
+            .reattach(&connection, &name);
+
+        let hooks = Hooks::default();
+        app.state::<HookState>()
+            .0
+            .lock()
+            .unwrap()
+            .insert(name.clone(), hooks.clone());
+
+        let emitter = app.clone();
+        let hook_name = name.clone();
+        let pending = hooks.pending.clone();
+        register_hooks(&connection, name.clone(), &hooks, move |event| {
+            match event {
+                HookEvent::Change(change) => {
+                    let _ = emitter.emit_all("rusqlite://change", change);
+                }
+                HookEvent::Committing => {
+                    let state = emitter.state::<ConfigState>();
+                    let mut committing = state.committing.lock().unwrap();
+                    committing.insert(hook_name.clone(), pending.clone());
+                }
+                HookEvent::Rollback(rollback) => {
+                    let _ = emitter.emit_all("rusqlite://rollback", rollback);
+                }
+            };
+        });
+
+        connections.insert(name.clone(), SharedConnection::new(connection));
     }
 
     Ok(())
 }
 
+/// Fails unless the filesystem scope of the application allows `path`, for the commands reading
+/// or writing a file named by the webview.
+fn check_path<R: Runtime>(app: &AppHandle<R>, path: &str) -> Result<()> {
+    if app.fs_scope().is_allowed(path) {
+        Ok(())
+    } else {
+        Err(Error::Path(path.to_string()))
+    }
+}
+
+/// Runs `f` on the changes pending on the connection, which the savepoint commands keep in step
+/// with the savepoints they open and roll back.
+fn with_pending<T>(
+    hook_state: &HookState,
+    name: &str,
+    f: impl FnOnce(&mut PendingChanges) -> T,
+) -> Option<T> {
+    let pending = hook_state.0.lock().unwrap().get(name)?.pending.clone();
+    let result = f(&mut pending.lock().unwrap());
+    Some(result)
+}
+
+/// Runs a command working inside a savepoint, which is rolled back when it fails, so the changes
+/// it recorded are forgotten then.
+fn discard_on_error<T>(
+    hook_state: &HookState,
+    name: &str,
+    f: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    let mark = with_pending(hook_state, name, |pending| pending.mark());
+    let result = f();
+    if let (Err(_), Some(mark)) = (&result, mark) {
+        with_pending(hook_state, name, |pending| pending.discard_since(mark));
+    }
+    result
+}
+
+/// Runs again the watched selects reading a table modified by the transaction. It runs on the
+/// thread confirming the commits, once the connection has been released.
+fn refresh_watches<R: Runtime>(app: &AppHandle<R>, commit: &TransactionChanges) {
+    let watches = app
+        .state::<WatchState>()
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, watch)| {
+            watch.name == commit.name
+                && commit
+                    .tables
+                    .iter()
+                    .any(|table| watch.tables.contains(table))
+        })
+        .map(|(id, watch)| (*id, watch.sql.clone(), watch.parameters.clone()))
+        .collect::<Vec<_>>();
+    if watches.is_empty() {
+        return;
+    }
+
+    let state = app.state::<ConfigState>();
+    let connections = state.lock();
+    let connection = match connections.get(&commit.name) {
+        Some(connection) => connection,
+        None => return,
+    };
+    for (id, sql, parameters) in watches {
+        let update = match execute_select(connection, sql, parameters) {
+            Ok(rows) => WatchUpdate {
+                id,
+                rows,
+                error: None,
+            },
+            Err(error) => WatchUpdate {
+                id,
+                rows: ResultList::new(),
+                error: Some(error.to_string()),
+            },
+        };
+        let _ = app.emit_all(&format!("rusqlite://watch/{}", id), update);
+    }
+}
+
 #[command]
 async fn migration(
     state: State<'_, ConfigState>,
     name: String,
     migrations: Migrations,
 ) -> Result<()> {
-    let connections = state.0.lock().unwrap();
+    let connections = state.lock();
     let connection = match connections.get(&name) {
         Some(connection) => connection,
         None => return Err(Error::Connection()),
@@ -75,7 +367,7 @@ async fn update(
     sql: String,
     parameters: HashMap<String, JsonValue>,
 ) -> Result<()> {
-    let connections = state.0.lock().unwrap();
+    let connections = state.lock();
     let connection = match connections.get(&name) {
         Some(connection) => connection,
         None => return Err(Error::Connection()),
@@ -84,62 +376,963 @@ async fn update(
     execute_update(connection, sql, parameters)
 }
 
+#[command]
+async fn update_many(
+    state: State<'_, ConfigState>,
+    hook_state: State<'_, HookState>,
+    name: String,
+    sql: String,
+    parameters: Vec<HashMap<String, JsonValue>>,
+) -> Result<Vec<UpdateResult>> {
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    discard_on_error(&hook_state, &name, || {
+        execute_update_many(connection, sql, parameters)
+    })
+}
+
 #[command]
 async fn select(
     state: State<'_, ConfigState>,
     name: String,
     sql: String,
     parameters: HashMap<String, JsonValue>,
+    with_metadata: Option<bool>,
+) -> Result<SelectResult> {
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    if with_metadata.unwrap_or(false) {
+        execute_select_with_metadata(connection, sql, parameters).map(SelectResult::WithMetadata)
+    } else {
+        execute_select(connection, sql, parameters).map(SelectResult::Rows)
+    }
+}
+
+#[command]
+async fn schema(
+    state: State<'_, ConfigState>,
+    name: String,
+    sql: String,
+) -> Result<Vec<ColumnMetadata>> {
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    execute_schema(connection, sql)
+}
+
+#[command]
+async fn tables(
+    state: State<'_, ConfigState>,
+    name: String,
+    schema: Option<String>,
+) -> Result<Vec<SchemaObject>> {
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    list_tables(connection, schema.as_deref().unwrap_or("main"))
+}
+
+#[command]
+async fn views(
+    state: State<'_, ConfigState>,
+    name: String,
+    schema: Option<String>,
+) -> Result<Vec<SchemaObject>> {
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    list_views(connection, schema.as_deref().unwrap_or("main"))
+}
+
+#[command]
+async fn triggers(
+    state: State<'_, ConfigState>,
+    name: String,
+    schema: Option<String>,
+) -> Result<Vec<SchemaObject>> {
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    list_triggers(connection, schema.as_deref().unwrap_or("main"))
+}
+
+#[command]
+async fn columns(
+    state: State<'_, ConfigState>,
+    name: String,
+    table: String,
+    schema: Option<String>,
+) -> Result<Vec<TableColumn>> {
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    list_columns(connection, schema.as_deref().unwrap_or("main"), &table)
+}
+
+#[command]
+async fn indexes(
+    state: State<'_, ConfigState>,
+    name: String,
+    table: String,
+    schema: Option<String>,
+) -> Result<Vec<TableIndex>> {
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    list_indexes(connection, schema.as_deref().unwrap_or("main"), &table)
+}
+
+#[command]
+async fn foreign_keys(
+    state: State<'_, ConfigState>,
+    name: String,
+    table: String,
+    schema: Option<String>,
+) -> Result<Vec<ForeignKey>> {
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    list_foreign_keys(connection, schema.as_deref().unwrap_or("main"), &table)
+}
+
+#[command]
+async fn select_one(
+    state: State<'_, ConfigState>,
+    name: String,
+    sql: String,
+    parameters: HashMap<String, JsonValue>,
+    strict: Option<bool>,
+) -> Result<Option<ResultElement>> {
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    execute_select_one(connection, sql, parameters, strict.unwrap_or(false))
+}
+
+#[command]
+async fn select_value(
+    state: State<'_, ConfigState>,
+    name: String,
+    sql: String,
+    parameters: HashMap<String, JsonValue>,
+) -> Result<JsonValue> {
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    execute_select_value(connection, sql, parameters)
+}
+
+#[command]
+async fn select_stream<R: Runtime>(
+    app: AppHandle<R>,
+    state: State<'_, ConfigState>,
+    name: String,
+    id: u32,
+    sql: String,
+    parameters: HashMap<String, JsonValue>,
+    chunk_size: Option<usize>,
+) -> Result<usize> {
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    let rows_event = format!("rusqlite://rows/{}", id);
+    let mut sent = 0;
+    let result = execute_select_stream(
+        connection,
+        sql,
+        parameters,
+        chunk_size.unwrap_or(500),
+        |chunk| {
+            let length = chunk.len();
+            app.emit_all(&rows_event, chunk)
+                .map_err(|error| Error::Event(error.to_string()))?;
+            sent += length;
+            Ok(())
+        },
+    );
+
+    let end = StreamEnd {
+        total: sent,
+        error: result.as_ref().err().map(|error| error.to_string()),
+    };
+    app.emit_all(&format!("rusqlite://done/{}", id), end)
+        .map_err(|error| Error::Event(error.to_string()))?;
+
+    result
+}
+
+#[command]
+async fn backup<R: Runtime>(
+    app: AppHandle<R>,
+    state: State<'_, ConfigState>,
+    name: String,
+    destination_path: String,
+    pages_per_step: Option<i32>,
+) -> Result<()> {
+    check_path(&app, &destination_path)?;
+    // The backup holds its own reference to the connection, which keeps it open, and takes the
+    // lock for each step only, leaving the connection to other commands meanwhile.
+    let connection = match state.lock().get(&name) {
+        Some(connection) => connection.clone(),
+        None => return Err(Error::Connection()),
+    };
+
+    let mut destination =
+        Connection::open_with_flags(destination_path.clone(), OpenFlags::default())
+            .map_err(|error| Error::OpeningConnection(error.to_string()))?;
+
+    execute_backup(
+        &connection,
+        &mut destination,
+        pages_per_step.unwrap_or(100),
+        || state.lock(),
+        |remaining, page_count| {
+            let progress = BackupProgress {
+                name: name.clone(),
+                destination_path: destination_path.clone(),
+                remaining,
+                page_count,
+            };
+            app.emit_all("rusqlite://backup", progress)
+                .map_err(|error| Error::Event(error.to_string()))
+        },
+    )
+}
+
+#[command]
+async fn restore<R: Runtime>(
+    app: AppHandle<R>,
+    state: State<'_, ConfigState>,
+    name: String,
+    source_path: String,
+) -> Result<()> {
+    check_path(&app, &source_path)?;
+    let mut connections = state.lock();
+    let connection = match connections.get_mut(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+    let connection = match connection.get_mut() {
+        Some(connection) => connection,
+        None => {
+            return Err(Error::Database(
+                "Cannot restore a database with open cursors or prepared statements".to_string(),
+            ))
+        }
+    };
+
+    let source = Connection::open_with_flags(source_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|error| Error::OpeningConnection(error.to_string()))?;
+
+    execute_restore(connection, &source)
+}
+
+#[command]
+async fn serialize(state: State<'_, ConfigState>, name: String) -> Result<Vec<u8>> {
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    execute_serialize(connection)
+}
+
+#[command]
+async fn vacuum(state: State<'_, ConfigState>, name: String) -> Result<VacuumResult> {
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    execute_vacuum(connection)
+}
+
+#[command]
+async fn vacuum_into<R: Runtime>(
+    app: AppHandle<R>,
+    state: State<'_, ConfigState>,
+    name: String,
+    path: String,
+) -> Result<VacuumResult> {
+    check_path(&app, &path)?;
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    execute_vacuum_into(connection, &path)
+}
+
+#[command]
+async fn rekey(state: State<'_, ConfigState>, name: String, key: String) -> Result<()> {
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    execute_rekey(connection, &key)
+}
+
+#[command]
+async fn attach<R: Runtime>(
+    app: AppHandle<R>,
+    state: State<'_, ConfigState>,
+    attachment_state: State<'_, AttachmentState>,
+    name: String,
+    path: String,
+    schema_alias: String,
+    options: Option<AttachOptions>,
+) -> Result<()> {
+    check_path(&app, &path)?;
+
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    let attachment = Attachment {
+        path,
+        schema: schema_alias,
+        options: options.unwrap_or_default(),
+    };
+    attachment_state
+        .0
+        .lock()
+        .unwrap()
+        .attach(connection, &name, attachment)?;
+    Ok(())
+}
+
+#[command]
+async fn detach(
+    state: State<'_, ConfigState>,
+    attachment_state: State<'_, AttachmentState>,
+    name: String,
+    schema_alias: String,
+) -> Result<()> {
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    attachment_state
+        .0
+        .lock()
+        .unwrap()
+        .detach(connection, &name, &schema_alias)
+}
+
+#[command]
+async fn open_cursor(
+    state: State<'_, ConfigState>,
+    cursor_state: State<'_, CursorState>,
+    name: String,
+    sql: String,
+    parameters: HashMap<String, JsonValue>,
+) -> Result<u64> {
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    let cursor = create_cursor(connection, name, sql, parameters)?;
+    let id = cursor_state.1.fetch_add(1, Ordering::Relaxed);
+    cursor_state.0.lock().unwrap().insert(id, cursor);
+
+    Ok(id)
+}
+
+#[command]
+async fn fetch(
+    state: State<'_, ConfigState>,
+    cursor_state: State<'_, CursorState>,
+    cursor: u64,
+    count: usize,
+) -> Result<ResultList> {
+    // The cursor steps through its statement on the connection, which must not be in use.
+    let _connections = state.lock();
+    let mut cursors = cursor_state.0.lock().unwrap();
+    let cursor_value = match cursors.get_mut(&cursor) {
+        Some(cursor_value) => cursor_value,
+        None => return Err(Error::Cursor(cursor)),
+    };
+
+    fetch_cursor(cursor_value, count)
+}
+
+#[command]
+async fn close_cursor(
+    state: State<'_, ConfigState>,
+    cursor_state: State<'_, CursorState>,
+    cursor: u64,
+) -> Result<()> {
+    let _connections = state.lock();
+    match cursor_state.0.lock().unwrap().remove(&cursor) {
+        Some(_) => Ok(()),
+        None => Err(Error::Cursor(cursor)),
+    }
+}
+
+#[command]
+async fn prepare(
+    state: State<'_, ConfigState>,
+    statement_state: State<'_, StatementState>,
+    name: String,
+    sql: String,
+) -> Result<u64> {
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    let prepared_statement = create_statement(connection, name, sql)?;
+    let id = statement_state.1.fetch_add(1, Ordering::Relaxed);
+    statement_state
+        .0
+        .lock()
+        .unwrap()
+        .insert(id, prepared_statement);
+
+    Ok(id)
+}
+
+#[command]
+async fn statement_update(
+    state: State<'_, ConfigState>,
+    statement_state: State<'_, StatementState>,
+    statement: u64,
+    parameters: HashMap<String, JsonValue>,
+) -> Result<()> {
+    // The statement runs on its connection, which must not be in use.
+    let _connections = state.lock();
+    let mut statements = statement_state.0.lock().unwrap();
+    let prepared_statement = match statements.get_mut(&statement) {
+        Some(prepared_statement) => prepared_statement,
+        None => return Err(Error::Statement(statement)),
+    };
+
+    execute_statement_update(prepared_statement, parameters)
+}
+
+#[command]
+async fn statement_select(
+    state: State<'_, ConfigState>,
+    statement_state: State<'_, StatementState>,
+    statement: u64,
+    parameters: HashMap<String, JsonValue>,
 ) -> Result<ResultList> {
-    let connections = state.0.lock().unwrap();
+    let _connections = state.lock();
+    let mut statements = statement_state.0.lock().unwrap();
+    let prepared_statement = match statements.get_mut(&statement) {
+        Some(prepared_statement) => prepared_statement,
+        None => return Err(Error::Statement(statement)),
+    };
+
+    execute_statement_select(prepared_statement, parameters)
+}
+
+#[command]
+async fn finalize(
+    state: State<'_, ConfigState>,
+    statement_state: State<'_, StatementState>,
+    statement: u64,
+) -> Result<()> {
+    let _connections = state.lock();
+    match statement_state.0.lock().unwrap().remove(&statement) {
+        Some(_) => Ok(()),
+        None => Err(Error::Statement(statement)),
+    }
+}
+
+#[command]
+async fn batch(
+    state: State<'_, ConfigState>,
+    hook_state: State<'_, HookState>,
+    name: String,
+    sql: String,
+    transaction: Option<bool>,
+) -> Result<()> {
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    if transaction.unwrap_or(false) {
+        discard_on_error(&hook_state, &name, || {
+            execute_batch_in_transaction(connection, sql)
+        })
+    } else {
+        execute_batch(connection, sql)
+    }
+}
+
+#[command]
+async fn script(
+    state: State<'_, ConfigState>,
+    name: String,
+    sql: String,
+    parameters: Option<Vec<HashMap<String, JsonValue>>>,
+    continue_on_error: Option<bool>,
+) -> Result<Vec<StatementResult>> {
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    execute_script(
+        connection,
+        sql,
+        parameters.unwrap_or_default(),
+        continue_on_error.unwrap_or(false),
+    )
+}
+
+#[command]
+async fn savepoint(
+    state: State<'_, ConfigState>,
+    hook_state: State<'_, HookState>,
+    name: String,
+    savepoint: String,
+) -> Result<()> {
+    let connections = state.lock();
     let connection = match connections.get(&name) {
         Some(connection) => connection,
         None => return Err(Error::Connection()),
     };
 
-    execute_select(connection, sql, parameters)
+    execute_savepoint(connection, &savepoint)?;
+    with_pending(&hook_state, &name, |pending| pending.savepoint(&savepoint));
+    Ok(())
 }
 
 #[command]
-async fn batch(state: State<'_, ConfigState>, name: String, sql: String) -> Result<()> {
-    let connections = state.0.lock().unwrap();
+async fn release(
+    state: State<'_, ConfigState>,
+    hook_state: State<'_, HookState>,
+    name: String,
+    savepoint: String,
+) -> Result<()> {
+    let connections = state.lock();
     let connection = match connections.get(&name) {
         Some(connection) => connection,
         None => return Err(Error::Connection()),
     };
 
-    execute_batch(connection, sql)
+    execute_release(connection, &savepoint)?;
+    with_pending(&hook_state, &name, |pending| pending.release(&savepoint));
+    Ok(())
 }
 
 #[command]
-async fn close(state: State<'_, ConfigState>, name: String) -> Result<()> {
-    let mut connections = state.0.lock().unwrap();
+async fn rollback_to(
+    state: State<'_, ConfigState>,
+    hook_state: State<'_, HookState>,
+    name: String,
+    savepoint: String,
+) -> Result<()> {
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    execute_rollback_to(connection, &savepoint)?;
+    with_pending(&hook_state, &name, |pending| {
+        pending.rollback_to(&savepoint)
+    });
+    Ok(())
+}
+
+#[command]
+async fn subscribe(
+    hook_state: State<'_, HookState>,
+    name: String,
+    tables: Vec<String>,
+) -> Result<()> {
+    require_feature(cfg!(feature = "hooks"), "hooks")?;
+    let hooks = hook_state.0.lock().unwrap();
+    let subscriptions = match hooks.get(&name) {
+        Some(hooks) => &hooks.subscriptions,
+        None => return Err(Error::Connection()),
+    };
+
+    subscriptions.lock().unwrap().extend(tables);
+    Ok(())
+}
+
+#[command]
+async fn unsubscribe(
+    hook_state: State<'_, HookState>,
+    name: String,
+    tables: Vec<String>,
+) -> Result<()> {
+    require_feature(cfg!(feature = "hooks"), "hooks")?;
+    let hooks = hook_state.0.lock().unwrap();
+    let subscriptions = match hooks.get(&name) {
+        Some(hooks) => &hooks.subscriptions,
+        None => return Err(Error::Connection()),
+    };
+
+    let mut subscriptions = subscriptions.lock().unwrap();
+    for table in tables {
+        subscriptions.remove(&table);
+    }
+    Ok(())
+}
+
+#[command]
+async fn watch(
+    state: State<'_, ConfigState>,
+    watch_state: State<'_, WatchState>,
+    name: String,
+    id: u32,
+    sql: String,
+    parameters: HashMap<String, JsonValue>,
+) -> Result<ResultList> {
+    require_feature(cfg!(feature = "hooks"), "hooks")?;
+    let connections = state.lock();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    let mut watches = watch_state.0.lock().unwrap();
+    if watches.contains_key(&id) {
+        return Err(Error::WatchExists(id));
+    }
+    let (watch, rows) = create_watch(connection, name, sql, parameters)?;
+    watches.insert(id, watch);
+
+    Ok(rows)
+}
+
+#[command]
+async fn unwatch(watch_state: State<'_, WatchState>, id: u32) -> Result<()> {
+    match watch_state.0.lock().unwrap().remove(&id) {
+        Some(_) => Ok(()),
+        None => Err(Error::Watch(id)),
+    }
+}
+
+#[command]
+#[allow(clippy::too_many_arguments)]
+async fn close(
+    state: State<'_, ConfigState>,
+    cursor_state: State<'_, CursorState>,
+    statement_state: State<'_, StatementState>,
+    hook_state: State<'_, HookState>,
+    watch_state: State<'_, WatchState>,
+    attachment_state: State<'_, AttachmentState>,
+    name: String,
+    optimize: Option<bool>,
+    forget_attachments: Option<bool>,
+) -> Result<()> {
+    let mut connections = state.lock();
+    let mut cursors = cursor_state.0.lock().unwrap();
+    let mut statements = statement_state.0.lock().unwrap();
+    let connection = match connections.get(&name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    // The cursors and statements of the connection are closed with it, but anything else still
+    // referring to it, such as a running backup, keeps it open and everything unchanged.
+    let references = 1
+        + cursors
+            .values()
+            .filter(|cursor| cursor.name == name)
+            .count()
+        + statements
+            .values()
+            .filter(|prepared_statement| prepared_statement.name == name)
+            .count();
+    if connection.references() > references {
+        return Err(Error::ClosingConnection(
+            "The connection is still in use".to_string(),
+        ));
+    }
+
+    if optimize.unwrap_or(false) {
+        // Optimizing is an extra that must not keep the database from closing.
+        let _ = execute_optimize(connection);
+    }
     let connection = match connections.remove(&name) {
         Some(connection) => connection,
         None => return Err(Error::Connection()),
     };
 
+    cursors.retain(|_, cursor| cursor.name != name);
+    statements.retain(|_, prepared_statement| prepared_statement.name != name);
+    hook_state.0.lock().unwrap().remove(&name);
+    watch_state
+        .0
+        .lock()
+        .unwrap()
+        .retain(|_, watch| watch.name != name);
+    if forget_attachments.unwrap_or(false) {
+        attachment_state.0.lock().unwrap().forget(&name);
+    }
+
+    let connection = match connection.into_inner() {
+        Some(connection) => connection,
+        None => {
+            return Err(Error::ClosingConnection(
+                "The connection is still in use".to_string(),
+            ))
+        }
+    };
     connection
         .close()
         .map_err(|(_, error)| Error::ClosingConnection(error.to_string()))?;
     Ok(())
 }
 
+/// Runs `f` with the connection opened under `name`, letting Rust code share the databases
+/// opened by the webview.
+pub fn with_connection<R, M, F, T>(manager: &M, name: &str, f: F) -> Result<T>
+where
+    R: Runtime,
+    M: Manager<R>,
+    F: FnOnce(&Connection) -> Result<T>,
+{
+    let state = match manager.try_state::<ConfigState>() {
+        Some(state) => state,
+        None => return Err(Error::Connection()),
+    };
+    let connections = state.lock();
+    let connection = match connections.get(name) {
+        Some(connection) => connection,
+        None => return Err(Error::Connection()),
+    };
+
+    f(connection)
+}
+
+/// Configures the plugin.
+#[derive(Default)]
+pub struct Builder {
+    initializers: Vec<ConnectionInitializer>,
+    key_provider: Option<KeyProvider>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the scalar SQL function `name` on every connection opened by the plugin.
+    /// `n_arg` is the number of arguments, or -1 for any number of arguments.
+    #[cfg(feature = "functions")]
+    pub fn scalar_function<F, T>(
+        mut self,
+        name: &str,
+        n_arg: i32,
+        flags: FunctionFlags,
+        function: F,
+    ) -> Self
+    where
+        F: Fn(&Context<'_>) -> rusqlite::Result<T>
+            + Send
+            + Sync
+            + std::panic::RefUnwindSafe
+            + 'static,
+        T: SqlFnOutput,
+    {
+        self.initializers
+            .push(scalar_function(name, n_arg, flags, function));
+        self
+    }
+
+    /// Creates the aggregate SQL function `name` on every connection opened by the plugin.
+    #[cfg(feature = "functions")]
+    pub fn aggregate_function<A, D, T>(
+        mut self,
+        name: &str,
+        n_arg: i32,
+        flags: FunctionFlags,
+        aggregate: D,
+    ) -> Self
+    where
+        A: std::panic::RefUnwindSafe + std::panic::UnwindSafe + 'static,
+        D: Aggregate<A, T> + Send + Sync + 'static,
+        T: SqlFnOutput + 'static,
+    {
+        self.initializers
+            .push(aggregate_function(name, n_arg, flags, aggregate));
+        self
+    }
+
+    /// Creates the aggregate window SQL function `name` on every connection opened by the plugin.
+    /// It can also be used as a regular aggregate function.
+    #[cfg(feature = "window")]
+    pub fn window_function<A, W, T>(
+        mut self,
+        name: &str,
+        n_arg: i32,
+        flags: FunctionFlags,
+        aggregate: W,
+    ) -> Self
+    where
+        A: std::panic::RefUnwindSafe + std::panic::UnwindSafe + 'static,
+        W: WindowAggregate<A, T> + Send + Sync + 'static,
+        T: SqlFnOutput + 'static,
+    {
+        self.initializers
+            .push(window_function(name, n_arg, flags, aggregate));
+        self
+    }
+
+    /// Creates the collation `name` on every connection opened by the plugin, to be used as
+    /// `ORDER BY name COLLATE <name>` or in column definitions.
+    #[cfg(feature = "collation")]
+    pub fn collation<C>(mut self, name: &str, compare: C) -> Self
+    where
+        C: Fn(&str, &str) -> std::cmp::Ordering + Send + Sync + std::panic::RefUnwindSafe + 'static,
+    {
+        self.initializers.push(collation(name, compare));
+        self
+    }
+
+    /// Creates the built-in collations on every connection opened by the plugin:
+    /// `UNICODE_CI` ignores case and accents, and `NATURAL_CI` also compares numbers by value.
+    #[cfg(feature = "unicode_collations")]
+    pub fn unicode_collations(self) -> Self {
+        self.collation("UNICODE_CI", unicode_ci)
+            .collation("NATURAL_CI", natural)
+    }
+
+    /// Supplies the SQLCipher key of the databases opened by `open_in_path` without a key, from
+    /// their path. Returning `None` opens the database without a key.
+    pub fn key_provider<F>(mut self, key_provider: F) -> Self
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.key_provider = Some(Arc::new(key_provider));
+        self
+    }
+
+    /// Builds the plugin.
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        let initializers = self.initializers;
+        let key_provider = self.key_provider;
+
+        tauri::plugin::Builder::new("rusqlite")
+            .invoke_handler(tauri::generate_handler![
+                open_in_memory,
+                open_in_path,
+                open_from_bytes,
+                migration,
+                update,
+                update_many,
+                select,
+                schema,
+                tables,
+                views,
+                triggers,
+                columns,
+                indexes,
+                foreign_keys,
+                select_one,
+                select_value,
+                select_stream,
+                backup,
+                restore,
+                serialize,
+                vacuum,
+                vacuum_into,
+                rekey,
+                attach,
+                detach,
+                open_cursor,
+                fetch,
+                close_cursor,
+                prepare,
+                statement_update,
+                statement_select,
+                finalize,
+                batch,
+                script,
+                savepoint,
+                release,
+                rollback_to,
+                subscribe,
+                unsubscribe,
+                watch,
+                unwatch,
+                close
+            ])
+            .setup(|app| {
+                let (sender, receiver) = mpsc::channel::<TransactionChanges>();
+                app.manage(ConfigState::new(sender));
+                app.manage(CursorState::default());
+                app.manage(StatementState::default());
+                app.manage(HookState::default());
+                let handle = app.clone();
+                std::thread::spawn(move || {
+                    for commit in receiver {
+                        refresh_watches(&handle, &commit);
+                        let _ = handle.emit_all("rusqlite://commit", commit);
+                    }
+                });
+                app.manage(WatchState::default());
+                app.manage(AttachmentState::default());
+                app.manage(InitializerState(initializers));
+                app.manage(KeyState(key_provider));
+                Ok(())
+            })
+            .build()
+    }
+}
+
 /// Initializes the plugin.
 pub fn init<R: Runtime>() -> TauriPlugin<R> {
-    Builder::new("rusqlite")
-        .invoke_handler(tauri::generate_handler![
-            open_in_memory,
-            open_in_path,
-            migration,
-            update,
-            select,
-            batch,
-            close
-        ])
-        .setup(|app| {
-            app.manage(ConfigState::default());
-            Ok(())
-        })
-        .build()
+    Builder::default().build()
 }
@@ -0,0 +1,284 @@
+use std::collections::{HashMap, HashSet};
+
+use rusqlite::Connection;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::commands::{
+    select::{execute_select, execute_select_one},
+    update::execute_update,
+};
+use crate::error::Error;
+use crate::types::Result;
+
+/// Typed query for Rust code, built on the same conversions as the webview commands.
+///
+/// Parameters are bound from any [`Serialize`] struct or map, each field becoming the named
+/// parameter `:field`. Fields the query does not use are skipped, so a struct may carry more
+/// fields than the query needs. Rows are mapped into [`DeserializeOwned`] types by column name.
+///
+/// ```ignore
+/// let users: Vec<User> = Query::new("SELECT * FROM users WHERE name = :name")
+///     .bind(&UserFilter { name: "Alice".to_string() })?
+///     .fetch_all(connection)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Query {
+    sql: String,
+    parameters: HashMap<String, JsonValue>,
+    // Names bound by `bind`, which are skipped when the statement does not use them.
+    fields: HashSet<String>,
+}
+
+impl Query {
+    pub fn new(sql: impl Into<String>) -> Self {
+        Self {
+            sql: sql.into(),
+            parameters: HashMap::new(),
+            fields: HashSet::new(),
+        }
+    }
+
+    /// Binds every field of `parameters` as a named parameter, skipping those the query does not
+    /// use.
+    pub fn bind<P: Serialize>(mut self, parameters: &P) -> Result<Self> {
+        let value = serde_json::to_value(parameters)
+            .map_err(|error| Error::Serialization(error.to_string()))?;
+        let fields = match value {
+            JsonValue::Object(fields) => fields,
+            value => {
+                return Err(Error::Serialization(format!(
+                    "Parameters must serialize to a map, got {}",
+                    value
+                )))
+            }
+        };
+
+        for (name, value) in fields {
+            let name = parameter_name(&name);
+            self.fields.insert(name.clone());
+            self.parameters.insert(name, value);
+        }
+        Ok(self)
+    }
+
+    /// Binds a single named parameter.
+    pub fn bind_value<V: Serialize>(mut self, name: &str, value: V) -> Result<Self> {
+        let value =
+            serde_json::to_value(value).map_err(|error| Error::Serialization(error.to_string()))?;
+        let name = parameter_name(name);
+        self.fields.remove(&name);
+        self.parameters.insert(name, value);
+        Ok(self)
+    }
+
+    pub fn fetch_all<T: DeserializeOwned>(self, connection: &Connection) -> Result<Vec<T>> {
+        let (sql, parameters) = self.into_parameters(connection)?;
+        execute_select(connection, sql, parameters)?
+            .into_iter()
+            .map(|row| from_row(row.into()))
+            .collect()
+    }
+
+    pub fn fetch_optional<T: DeserializeOwned>(self, connection: &Connection) -> Result<Option<T>> {
+        let (sql, parameters) = self.into_parameters(connection)?;
+        execute_select_one(connection, sql, parameters, false)?
+            .map(|row| from_row(row.into()))
+            .transpose()
+    }
+
+    pub fn fetch_one<T: DeserializeOwned>(self, connection: &Connection) -> Result<T> {
+        self.fetch_optional(connection)?
+            .ok_or_else(|| Error::Database("The query returned no rows".to_string()))
+    }
+
+    pub fn execute(self, connection: &Connection) -> Result<()> {
+        let (sql, parameters) = self.into_parameters(connection)?;
+        execute_update(connection, sql, parameters)
+    }
+
+    /// Drops the fields bound by `bind` that the statement has no parameter for. The statement is
+    /// compiled into the statement cache, where running the query finds it again.
+    fn into_parameters(
+        self,
+        connection: &Connection,
+    ) -> Result<(String, HashMap<String, JsonValue>)> {
+        let statement = connection
+            .prepare_cached(&self.sql)
+            .map_err(|error| Error::Database(error.to_string()))?;
+
+        let fields = self.fields;
+        let mut parameters = self.parameters;
+        parameters.retain(|name, _| {
+            !fields.contains(name) || matches!(statement.parameter_index(name), Ok(Some(_)))
+        });
+        drop(statement);
+
+        Ok((self.sql, parameters))
+    }
+}
+
+fn parameter_name(name: &str) -> String {
+    if name.starts_with([':', '@', '$']) {
+        name.to_string()
+    } else {
+        format!(":{}", name)
+    }
+}
+
+fn from_row<T: DeserializeOwned>(row: JsonValue) -> Result<T> {
+    serde_json::from_value(row).map_err(|error| Error::Serialization(error.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct User {
+        id: i64,
+        name: String,
+        score: Option<f64>,
+        avatar: Option<Vec<u8>>,
+    }
+
+    #[derive(Serialize)]
+    struct NewUser<'a> {
+        name: &'a str,
+        score: Option<f64>,
+    }
+
+    fn create_connection() -> Connection {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, score REAL, avatar BLOB);",
+            )
+            .unwrap();
+        connection
+    }
+
+    #[test]
+    fn query_execute_and_fetch_test() {
+        let connection = create_connection();
+
+        let sql = "INSERT INTO users (name, score) VALUES (:name, :score)";
+        Query::new(sql)
+            .bind(&NewUser {
+                name: "Alice",
+                score: Some(1.5),
+            })
+            .unwrap()
+            .execute(&connection)
+            .unwrap();
+        Query::new(sql)
+            .bind(&NewUser {
+                name: "Bob",
+                score: None,
+            })
+            .unwrap()
+            .execute(&connection)
+            .unwrap();
+
+        let users: Vec<User> = Query::new("SELECT * FROM users ORDER BY id")
+            .fetch_all(&connection)
+            .unwrap();
+        assert_eq!(
+            users,
+            vec![
+                User {
+                    id: 1,
+                    name: "Alice".to_string(),
+                    score: Some(1.5),
+                    avatar: None,
+                },
+                User {
+                    id: 2,
+                    name: "Bob".to_string(),
+                    score: None,
+                    avatar: None,
+                },
+            ]
+        );
+
+        let user: Option<User> = Query::new("SELECT * FROM users WHERE name = :name")
+            .bind_value("name", "Bob")
+            .unwrap()
+            .fetch_optional(&connection)
+            .unwrap();
+        assert_eq!(user.unwrap().id, 2);
+
+        let user: Result<User> = Query::new("SELECT * FROM users WHERE id = :id")
+            .bind_value(":id", 3)
+            .unwrap()
+            .fetch_one(&connection);
+        assert!(user.is_err());
+    }
+
+    #[test]
+    fn query_bind_non_map_test() {
+        let result = Query::new("SELECT 1").bind(&1);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn query_bind_unused_fields_test() {
+        let connection = create_connection();
+
+        let user = User {
+            id: 1,
+            name: "Alice".to_string(),
+            score: None,
+            avatar: Some(vec![1, 2]),
+        };
+        Query::new("INSERT INTO users (id, name) VALUES (:id, :name)")
+            .bind(&user)
+            .unwrap()
+            .execute(&connection)
+            .unwrap();
+
+        let users: Vec<User> = Query::new("SELECT * FROM users WHERE name = :name")
+            .bind(&user)
+            .unwrap()
+            .fetch_all(&connection)
+            .unwrap();
+        assert_eq!(users[0].name, "Alice");
+        assert_eq!(users[0].avatar, None);
+
+        let result = Query::new("SELECT * FROM users")
+            .bind_value("name", "Alice")
+            .unwrap()
+            .fetch_all::<User>(&connection);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn query_bind_bool_test() {
+        let connection = create_connection();
+        connection
+            .execute_batch("CREATE TABLE flags (id INTEGER PRIMARY KEY, enabled INTEGER NOT NULL);")
+            .unwrap();
+
+        Query::new("INSERT INTO flags (enabled) VALUES (:enabled), (:disabled)")
+            .bind_value("enabled", true)
+            .unwrap()
+            .bind_value("disabled", false)
+            .unwrap()
+            .execute(&connection)
+            .unwrap();
+
+        let values: Vec<(i64, String)> = connection
+            .prepare("SELECT enabled, typeof(enabled) FROM flags ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            values,
+            vec![(1, "integer".to_string()), (0, "integer".to_string())]
+        );
+    }
+}
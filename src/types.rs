@@ -1,9 +1,157 @@
 use crate::error::Error;
-use rusqlite::ToSql;
+use rusqlite::{Connection, ToSql};
+use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value as JsonValue};
+use std::sync::Arc;
 
 pub type Result<T> = std::result::Result<T, Error>;
 pub type Migrations = Vec<JsonValue>;
 pub type SQLParameter = (String, Box<dyn ToSql>);
 pub type ResultElement = Map<String, JsonValue>;
 pub type ResultList = Vec<ResultElement>;
+pub type ConnectionInitializer = Arc<dyn Fn(&Connection) -> rusqlite::Result<()> + Send + Sync>;
+pub type KeyProvider = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnMetadata {
+    pub name: String,
+    pub decl_type: Option<String>,
+    pub database_name: Option<String>,
+    pub table_name: Option<String>,
+    pub origin_name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResultSet {
+    pub columns: Vec<ColumnMetadata>,
+    pub rows: ResultList,
+}
+
+/// Rows returned by `select`, along with the metadata of their columns when requested.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum SelectResult {
+    Rows(ResultList),
+    WithMetadata(ResultSet),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateResult {
+    pub changes: usize,
+    pub last_insert_rowid: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum StatementResult {
+    Rows {
+        rows: ResultList,
+    },
+    #[serde(rename_all = "camelCase")]
+    Changes {
+        changes: usize,
+        last_insert_rowid: i64,
+    },
+    Error {
+        offset: usize,
+        error: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Change {
+    pub name: String,
+    pub database: String,
+    pub table: String,
+    pub operation: String,
+    pub rowid: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransactionChanges {
+    pub name: String,
+    pub tables: Vec<String>,
+    pub changes: Vec<Change>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchUpdate {
+    pub id: u32,
+    pub rows: ResultList,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamEnd {
+    pub total: usize,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VacuumResult {
+    pub size_before: u64,
+    pub size_after: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupProgress {
+    pub name: String,
+    pub destination_path: String,
+    pub remaining: i32,
+    pub page_count: i32,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachOptions {
+    pub read_only: Option<bool>,
+    pub key: Option<String>,
+}
+
+/// A table, view or trigger of a database, as recorded in `sqlite_schema`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaObject {
+    pub name: String,
+    pub table_name: String,
+    pub sql: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableColumn {
+    pub name: String,
+    pub decl_type: Option<String>,
+    pub not_null: bool,
+    pub default_value: Option<String>,
+    /// Position of the column in the primary key, starting at 1, or 0 if it is not part of it.
+    pub primary_key: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableIndex {
+    pub name: String,
+    pub unique: bool,
+    /// `c` for indexes created with `CREATE INDEX`, `u` for `UNIQUE` constraints and `pk` for
+    /// primary keys.
+    pub origin: String,
+    pub partial: bool,
+    pub columns: Vec<Option<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForeignKey {
+    pub id: i64,
+    pub table: String,
+    pub from: Vec<String>,
+    /// Referenced columns, or `None` when the key references the primary key of `table`.
+    pub to: Vec<Option<String>>,
+    pub on_update: String,
+    pub on_delete: String,
+}
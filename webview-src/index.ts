@@ -1,4 +1,5 @@
 import { invoke } from '@tauri-apps/api/tauri'
+import { listen, once, UnlistenFn } from '@tauri-apps/api/event'
 
 export default class Rusqlite {
   name: string;
@@ -7,12 +8,20 @@ export default class Rusqlite {
     this.name = name;
   }
   
-  static async openInMemory(name: string): Promise<Rusqlite> {
-    return await invoke('plugin:rusqlite|open_in_memory', {name: name}).then(() => new Rusqlite(name));
+  static async openInMemory(name: string, cacheCapacity?: number): Promise<Rusqlite> {
+    return await invoke('plugin:rusqlite|open_in_memory', {name: name, cacheCapacity}).then(() => new Rusqlite(name));
   }
 
-  static async openInPath(path: string): Promise<Rusqlite> {
-    return await invoke('plugin:rusqlite|open_in_path', {path: path}).then(() => new Rusqlite(path));
+  static async openInPath(path: string, cacheCapacity?: number, key?: string): Promise<Rusqlite> {
+    return await invoke('plugin:rusqlite|open_in_path', {path: path, cacheCapacity, key}).then(() => new Rusqlite(path));
+  }
+
+  static async openFromBytes(name: string, bytes: Uint8Array, readOnly?: boolean, cacheCapacity?: number): Promise<Rusqlite> {
+    return await invoke('plugin:rusqlite|open_from_bytes', {name, bytes: Array.from(bytes), readOnly, cacheCapacity}).then(() => new Rusqlite(name));
+  }
+
+  async serialize(): Promise<Uint8Array> {
+    return await invoke<number[]>('plugin:rusqlite|serialize', {name: this.name}).then((bytes) => new Uint8Array(bytes));
   }
 
   async migration(migrations: Migration[]): Promise<void> {
@@ -23,16 +32,204 @@ export default class Rusqlite {
     return await invoke('plugin:rusqlite|update', {name: this.name, sql, parameters});
   }
 
-  async select(sql: string, parameters:Map<string, any>): Promise<any[]> {
+  async updateMany(sql: string, parameters: Map<string, any>[]): Promise<UpdateResult[]> {
+    return await invoke('plugin:rusqlite|update_many', {name: this.name, sql, parameters});
+  }
+
+  async select<T = any>(sql: string, parameters: Map<string, any>): Promise<T[]> {
     return await invoke('plugin:rusqlite|select', {name: this.name, sql, parameters});
   }
 
-  async batch(sql: string): Promise<void> {
-    return await invoke('plugin:rusqlite|batch', {name: this.name, sql});
+  async schema(sql: string): Promise<ColumnMetadata[]> {
+    return await invoke('plugin:rusqlite|schema', {name: this.name, sql});
+  }
+
+  async tables(schema?: string): Promise<SchemaObject[]> {
+    return await invoke('plugin:rusqlite|tables', {name: this.name, schema});
+  }
+
+  async views(schema?: string): Promise<SchemaObject[]> {
+    return await invoke('plugin:rusqlite|views', {name: this.name, schema});
+  }
+
+  async triggers(schema?: string): Promise<SchemaObject[]> {
+    return await invoke('plugin:rusqlite|triggers', {name: this.name, schema});
+  }
+
+  async columns(table: string, schema?: string): Promise<TableColumn[]> {
+    return await invoke('plugin:rusqlite|columns', {name: this.name, table, schema});
+  }
+
+  async indexes(table: string, schema?: string): Promise<TableIndex[]> {
+    return await invoke('plugin:rusqlite|indexes', {name: this.name, table, schema});
+  }
+
+  async foreignKeys(table: string, schema?: string): Promise<ForeignKey[]> {
+    return await invoke('plugin:rusqlite|foreign_keys', {name: this.name, table, schema});
+  }
+
+  async selectOne<T = any>(sql: string, parameters: Map<string, any>, strict?: boolean): Promise<T | null> {
+    return await invoke('plugin:rusqlite|select_one', {name: this.name, sql, parameters, strict});
+  }
+
+  async selectValue<T = any>(sql: string, parameters: Map<string, any>): Promise<T | null> {
+    return await invoke('plugin:rusqlite|select_value', {name: this.name, sql, parameters});
+  }
+
+  async selectWithMetadata<T = any>(sql: string, parameters: Map<string, any>): Promise<ResultSet<T>> {
+    return await invoke('plugin:rusqlite|select', {name: this.name, sql, parameters, withMetadata: true});
+  }
+
+  async selectStream<T = any>(sql: string, parameters: Map<string, any>, onRows: (rows: T[]) => void, chunkSize?: number): Promise<number> {
+    const id = window.crypto.getRandomValues(new Uint32Array(1))[0];
+    let resolveEnd: (end: StreamEnd) => void = () => {};
+    const end = new Promise<StreamEnd>((resolve) => resolveEnd = resolve);
+    const unlistenRows = await listen<T[]>(`rusqlite://rows/${id}`, (event) => onRows(event.payload));
+    const unlistenEnd = await once<StreamEnd>(`rusqlite://done/${id}`, (event) => resolveEnd(event.payload));
+    try {
+      await invoke('plugin:rusqlite|select_stream', {name: this.name, id, sql, parameters, chunkSize});
+      return (await end).total;
+    } finally {
+      unlistenRows();
+      unlistenEnd();
+    }
+  }
+
+  async backup(destinationPath: string, pagesPerStep?: number, onProgress?: (progress: BackupProgress) => void): Promise<void> {
+    const unlisten = await listen<BackupProgress>('rusqlite://backup', (event) => {
+      if (event.payload.name === this.name && event.payload.destinationPath === destinationPath) {
+        onProgress?.(event.payload);
+      }
+    });
+    try {
+      await invoke('plugin:rusqlite|backup', {name: this.name, destinationPath, pagesPerStep});
+    } finally {
+      unlisten();
+    }
+  }
+
+  async restore(sourcePath: string): Promise<void> {
+    return await invoke('plugin:rusqlite|restore', {name: this.name, sourcePath});
+  }
+
+  async openCursor(sql: string, parameters: Map<string, any>): Promise<number> {
+    return await invoke('plugin:rusqlite|open_cursor', {name: this.name, sql, parameters});
+  }
+
+  async fetch<T = any>(cursor: number, count: number): Promise<T[]> {
+    return await invoke('plugin:rusqlite|fetch', {cursor, count});
+  }
+
+  async closeCursor(cursor: number): Promise<void> {
+    return await invoke('plugin:rusqlite|close_cursor', {cursor});
+  }
+
+  async prepare(sql: string): Promise<number> {
+    return await invoke('plugin:rusqlite|prepare', {name: this.name, sql});
+  }
+
+  async statementUpdate(statement: number, parameters: Map<string, any>): Promise<void> {
+    return await invoke('plugin:rusqlite|statement_update', {statement, parameters});
+  }
+
+  async statementSelect<T = any>(statement: number, parameters: Map<string, any>): Promise<T[]> {
+    return await invoke('plugin:rusqlite|statement_select', {statement, parameters});
+  }
+
+  async finalize(statement: number): Promise<void> {
+    return await invoke('plugin:rusqlite|finalize', {statement});
+  }
+
+  async batch(sql: string, transaction?: boolean): Promise<void> {
+    return await invoke('plugin:rusqlite|batch', {name: this.name, sql, transaction});
+  }
+
+  async script(sql: string, parameters?: Map<string, any>[], continueOnError?: boolean): Promise<StatementResult[]> {
+    return await invoke('plugin:rusqlite|script', {name: this.name, sql, parameters, continueOnError});
+  }
+
+  async savepoint(savepoint: string): Promise<void> {
+    return await invoke('plugin:rusqlite|savepoint', {name: this.name, savepoint});
+  }
+
+  async release(savepoint: string): Promise<void> {
+    return await invoke('plugin:rusqlite|release', {name: this.name, savepoint});
+  }
+
+  async rollbackTo(savepoint: string): Promise<void> {
+    return await invoke('plugin:rusqlite|rollback_to', {name: this.name, savepoint});
+  }
+
+  async subscribe(tables: string[]): Promise<void> {
+    return await invoke('plugin:rusqlite|subscribe', {name: this.name, tables});
   }
 
-  async close(): Promise<void> {
-    return await invoke('plugin:rusqlite|close', {name: this.name});
+  async unsubscribe(tables: string[]): Promise<void> {
+    return await invoke('plugin:rusqlite|unsubscribe', {name: this.name, tables});
+  }
+
+  async onChange(handler: (change: Change) => void): Promise<UnlistenFn> {
+    return await listen<Change>('rusqlite://change', (event) => {
+      if (event.payload.name === this.name) {
+        handler(event.payload);
+      }
+    });
+  }
+
+  async onCommit(handler: (commit: TransactionChanges) => void): Promise<UnlistenFn> {
+    return await listen<TransactionChanges>('rusqlite://commit', (event) => {
+      if (event.payload.name === this.name) {
+        handler(event.payload);
+      }
+    });
+  }
+
+  async onRollback(handler: (rollback: TransactionChanges) => void): Promise<UnlistenFn> {
+    return await listen<TransactionChanges>('rusqlite://rollback', (event) => {
+      if (event.payload.name === this.name) {
+        handler(event.payload);
+      }
+    });
+  }
+
+  async watch<T = any>(sql: string, parameters: Map<string, any>, handler: (update: WatchUpdate<T>) => void): Promise<() => Promise<void>> {
+    const id = window.crypto.getRandomValues(new Uint32Array(1))[0];
+    const unlisten = await listen<WatchUpdate<T>>(`rusqlite://watch/${id}`, (event) => handler(event.payload));
+    try {
+      const rows = await invoke<T[]>('plugin:rusqlite|watch', {name: this.name, id, sql, parameters});
+      handler({id, rows, error: null});
+    } catch (error) {
+      unlisten();
+      throw error;
+    }
+    return async () => {
+      unlisten();
+      await invoke('plugin:rusqlite|unwatch', {id});
+    };
+  }
+
+  async attach(path: string, schemaAlias: string, options?: AttachOptions): Promise<void> {
+    return await invoke('plugin:rusqlite|attach', {name: this.name, path, schemaAlias, options});
+  }
+
+  async detach(schemaAlias: string): Promise<void> {
+    return await invoke('plugin:rusqlite|detach', {name: this.name, schemaAlias});
+  }
+
+  async rekey(key: string): Promise<void> {
+    return await invoke('plugin:rusqlite|rekey', {name: this.name, key});
+  }
+
+  async vacuum(): Promise<VacuumResult> {
+    return await invoke('plugin:rusqlite|vacuum', {name: this.name});
+  }
+
+  async vacuumInto(path: string): Promise<VacuumResult> {
+    return await invoke('plugin:rusqlite|vacuum_into', {name: this.name, path});
+  }
+
+  async close(optimize?: boolean, forgetAttachments?: boolean): Promise<void> {
+    return await invoke('plugin:rusqlite|close', {name: this.name, optimize, forgetAttachments});
   }
 }
 
@@ -40,3 +237,99 @@ export interface Migration {
   name: string;
   sql: string;
 }
+
+export interface UpdateResult {
+  changes: number;
+  lastInsertRowid: number;
+}
+
+export type StatementResult =
+  | { type: 'rows', rows: any[] }
+  | { type: 'changes', changes: number, lastInsertRowid: number }
+  | { type: 'error', offset: number, error: string };
+
+export interface ColumnMetadata {
+  name: string;
+  declType: string | null;
+  databaseName: string | null;
+  tableName: string | null;
+  originName: string | null;
+}
+
+export interface ResultSet<T = any> {
+  columns: ColumnMetadata[];
+  rows: T[];
+}
+
+export interface Change {
+  name: string;
+  database: string;
+  table: string;
+  operation: 'insert' | 'update' | 'delete';
+  rowid: number;
+}
+
+export interface TransactionChanges {
+  name: string;
+  tables: string[];
+  changes: Change[];
+}
+
+export interface WatchUpdate<T = any> {
+  id: number;
+  rows: T[];
+  error: string | null;
+}
+
+export interface StreamEnd {
+  total: number;
+  error: string | null;
+}
+
+export interface SchemaObject {
+  name: string;
+  tableName: string;
+  sql: string | null;
+}
+
+export interface TableColumn {
+  name: string;
+  declType: string | null;
+  notNull: boolean;
+  defaultValue: string | null;
+  primaryKey: number;
+}
+
+export interface TableIndex {
+  name: string;
+  unique: boolean;
+  origin: 'c' | 'u' | 'pk';
+  partial: boolean;
+  columns: (string | null)[];
+}
+
+export interface ForeignKey {
+  id: number;
+  table: string;
+  from: string[];
+  to: (string | null)[];
+  onUpdate: string;
+  onDelete: string;
+}
+
+export interface AttachOptions {
+  readOnly?: boolean;
+  key?: string;
+}
+
+export interface VacuumResult {
+  sizeBefore: number;
+  sizeAfter: number;
+}
+
+export interface BackupProgress {
+  name: string;
+  destinationPath: string;
+  remaining: number;
+  pageCount: number;
+}